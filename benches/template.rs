@@ -0,0 +1,127 @@
+//! `cargo bench`でテンプレートの描画方法ごとの時間を比べる。
+//! `regex`は`Template`を導入する前の`compact!`と同じく、変数ごとに`Regex`を作って置換する
+use std::{
+    fs,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use blog_parser::{
+    context,
+    template::{cache::TemplateCache, Context, Template},
+};
+use regex::Regex;
+
+const PAGES: usize = 500;
+
+const TEMPLATE: &str = "<!DOCTYPE html>
+<html>
+<head>
+<title>{{ $title }}</title>
+<meta name=\"description\" content=\"{{ $description }}\">
+</head>
+<body>
+<h1>{{ $title }}</h1>
+<time>{{ $date }}</time>
+<p>{{ $reading_time }}分で読めます({{ $char_count }}文字)</p>
+<main>{{ $content }}</main>
+</body>
+</html>
+";
+
+struct Page {
+    title: String,
+    description: String,
+    date: String,
+    reading_time: String,
+    char_count: String,
+    content: String,
+}
+
+fn pages() -> Vec<Page> {
+    (0..PAGES)
+        .map(|i| Page {
+            title: format!("記事{}", i),
+            description: format!("記事{}の説明", i),
+            date: "2024-04-01".to_string(),
+            reading_time: (i % 10 + 1).to_string(),
+            char_count: (i * 100).to_string(),
+            content: "<p>本文</p>\n".repeat(50),
+        })
+        .collect()
+}
+
+fn regex_compact(page: &Page) -> String {
+    let mut html = TEMPLATE.to_string();
+    for (name, value) in [
+        ("title", &page.title),
+        ("description", &page.description),
+        ("date", &page.date),
+        ("reading_time", &page.reading_time),
+        ("char_count", &page.char_count),
+        ("content", &page.content),
+    ] {
+        let re = Regex::new(&format!(r"\{{\{{\s*\${}\s*\}}\}}", name)).unwrap();
+        html = re.replace_all(&html, value.replace('$', "$$")).into_owned();
+    }
+    html
+}
+
+fn context(page: &Page) -> Context {
+    let Page {
+        title,
+        description,
+        date,
+        reading_time,
+        char_count,
+        content,
+    } = page;
+    context!(title, description, date, reading_time, char_count, content)
+}
+
+fn bench(name: &str, pages: &[Page], mut render: impl FnMut(&Page) -> String) -> Duration {
+    // 1回目は計測しない
+    for page in pages {
+        black_box(render(page));
+    }
+    let start = Instant::now();
+    for page in pages {
+        black_box(render(page));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<16} {:>10.3?} ({:.3?}/page)",
+        name,
+        elapsed,
+        elapsed / pages.len() as u32
+    );
+    elapsed
+}
+
+fn main() {
+    let pages = pages();
+    println!("{} pages", pages.len());
+
+    let regex = bench("regex compact!", &pages, regex_compact);
+    bench("parse + render", &pages, |page| {
+        Template::parse(TEMPLATE).unwrap().render(&context(page))
+    });
+    let template = Template::parse(TEMPLATE).unwrap();
+    let compiled = bench("compiled", &pages, |page| template.render(&context(page)));
+
+    // キャッシュは描画のたびにファイルの更新日時を確認する
+    let dir = std::env::temp_dir().join(format!("blog-parser-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("page.html");
+    fs::write(&path, TEMPLATE).unwrap();
+    let mut cache = TemplateCache::new();
+    bench("cached by path", &pages, |page| {
+        cache.get(&path).unwrap().render(&context(page))
+    });
+    fs::remove_dir_all(dir).unwrap();
+
+    println!(
+        "compiled is {:.1}x faster than regex compact!",
+        regex.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
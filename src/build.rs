@@ -0,0 +1,716 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fs,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use chrono::{DateTime, FixedOffset, Local};
+
+use crate::{
+    config::Config,
+    data::{self, Data},
+    error::{Error, Result},
+    feed::write_feeds,
+    index::write_index,
+    manifest::{hash, Manifest},
+    markdown_to_html,
+    publish::{insert_banner, Status},
+    sitemap::write_sitemap,
+    tags::{parse_tags, tag_value, write_tags},
+    template::{cache::TemplateCache, Template, ToValue},
+    Rendered,
+};
+
+/// `build`の結果
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// 書き出したページのslug
+    pub built: Vec<String>,
+    /// 入力が前回から変わっていないので書き出さなかったページのslug
+    pub skipped: Vec<String>,
+    /// 下書きか公開前なので記事の一覧に入れなかったページのslug。プレビューでなければ書き出さない
+    pub unpublished: Vec<String>,
+    /// markdownがなくなったので一覧と出力から消したページのslug
+    pub removed: Vec<String>,
+    /// テンプレートで使われなかった変数(厳密モードのみ)
+    pub warnings: Vec<(PathBuf, String)>,
+    /// エラーになったmarkdownとそのエラー
+    pub errors: Vec<(PathBuf, Error)>,
+    /// 書き出した記事の一覧のページ数
+    pub index_pages: usize,
+    /// 書き出したタグごとの記事の一覧のページ数
+    pub tag_pages: usize,
+    /// `feed.xml`と`atom.xml`を書き出したかどうか
+    pub feeds: bool,
+    /// `sitemap.xml`と`robots.txt`を書き出したかどうか
+    pub sitemap: bool,
+}
+
+/// 書き出すページ
+struct Page {
+    path: PathBuf,
+    slug: String,
+    markdown: String,
+    /// 一覧にある作成日、なければ今日の日付
+    date: String,
+}
+
+/// `content_dir`の記事をすべて`output_dir/<slug>/index.html`に書き出し、最後に記事の一覧を一度だけ書き込んで
+/// 一覧のページ・タグのページ・フィード・サイトマップも書き出す。
+/// 記事はワーカースレッドで並列に書き出し、記事ごとのエラーは`Summary`に集めて残りの記事を続ける。
+/// markdown・テンプレート(`{% include %}`などで読むファイルも含む)・記事の一覧の項目が
+/// 前回のビルドから変わっていないページは書き出さない。markdownがなくなった記事は一覧と出力から消す。
+/// 下書きと`publish_at`が来ていない記事は一覧に入れず、`preview`なら公開前とわかる帯を付けて書き出す
+pub fn build(config: &Config, strict: bool, preview: bool) -> Result<Summary> {
+    build_with_cache(config, &mut TemplateCache::new(), strict, preview)
+}
+
+/// `build`と同じだが、テンプレートを`cache`から読む。`watch`のように何度もビルドする場合用
+pub fn build_with_cache(
+    config: &Config,
+    cache: &mut TemplateCache,
+    strict: bool,
+    preview: bool,
+) -> Result<Summary> {
+    let mut data = if config.data.exists() {
+        data::read(&config.data)?
+    } else {
+        VecDeque::new()
+    };
+    let template = cache.get(&config.template)?;
+    let templates = template
+        .files()
+        .iter()
+        .map(fs::read)
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let mut manifest = Manifest::load(&config.manifest)?;
+    let mut summary = Summary::default();
+    let today = data::today();
+    let now = Local::now().fixed_offset();
+
+    let mut pages = Vec::new();
+    // フィードには書き出さなかった記事の本文も使う
+    let mut sources = HashMap::new();
+    let mut slugs = HashSet::new();
+    for path in markdown_files(&config.content_dir)? {
+        let slug = slug(&config.content_dir, &path);
+        slugs.insert(slug.clone());
+        let markdown = match fs::read_to_string(&path) {
+            Ok(markdown) => markdown,
+            Err(err) => {
+                summary.errors.push((path, err.into()));
+                continue;
+            }
+        };
+
+        sources.insert(slug.clone(), markdown.clone());
+        let unchanged = manifest.pages.get(&slug)
+            == Some(&page_hash(
+                &markdown, &templates, &data, &slug, config, strict, preview,
+            ));
+        if unchanged && config.output_dir.join(&slug).join("index.html").exists() {
+            summary.skipped.push(slug);
+            continue;
+        }
+
+        let date = data::created_at(&data, &slug).unwrap_or(&today).to_string();
+        pages.push(Page {
+            path,
+            slug,
+            markdown,
+            date,
+        });
+    }
+
+    let results = parallel_map(&pages, config.jobs(), |page| {
+        build_page(config, &template, page, &data, strict, preview, now)
+    });
+
+    // 一覧はここでまとめて更新する
+    for (page, result) in pages.into_iter().zip(results) {
+        match result {
+            Ok((rendered, status, unused)) => {
+                summary
+                    .warnings
+                    .extend(unused.into_iter().map(|name| (page.path.clone(), name)));
+                if status.is_published() {
+                    data::upsert(
+                        &mut data,
+                        &page.slug,
+                        &rendered,
+                        &today,
+                        config.revision_limit,
+                    );
+                    // 一覧の項目は書き出した後のものでハッシュを取る
+                    let page_hash = page_hash(
+                        &page.markdown,
+                        &templates,
+                        &data,
+                        &page.slug,
+                        config,
+                        strict,
+                        preview,
+                    );
+                    manifest.pages.insert(page.slug.clone(), page_hash);
+                    summary.built.push(page.slug);
+                } else {
+                    // 公開予定の時刻を過ぎたら書き出すように、毎回確かめる
+                    data.retain(|data| data.slug != page.slug);
+                    manifest.pages.remove(&page.slug);
+                    if preview {
+                        summary.built.push(page.slug.clone());
+                    }
+                    summary.unpublished.push(page.slug);
+                }
+            }
+            Err(err) => {
+                manifest.pages.remove(&page.slug);
+                summary.errors.push((page.path, err));
+            }
+        }
+    }
+
+    summary.removed = remove_missing(config, &slugs, &mut data, &mut manifest)?;
+    data::write(&config.data, &data)?;
+    summary.index_pages = write_index(config, cache, &data)?;
+    summary.tag_pages = write_tags(config, cache, &data, &mut manifest)?;
+    manifest.save(&config.manifest)?;
+    summary.feeds = write_feeds(config, &data, &sources)?;
+    summary.sitemap = write_sitemap(config, &data, summary.index_pages)?;
+    Ok(summary)
+}
+
+/// `slugs`(見つかったmarkdown)にない記事を一覧・manifest・出力から消し、消したslugを返す
+fn remove_missing(
+    config: &Config,
+    slugs: &HashSet<String>,
+    data: &mut VecDeque<Data>,
+    manifest: &mut Manifest,
+) -> Result<Vec<String>> {
+    let removed = data
+        .iter()
+        .map(|data| &data.slug)
+        .chain(manifest.pages.keys())
+        .filter(|slug| !slugs.contains(*slug))
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    for slug in &removed {
+        // 記事の一覧を手で書き換えた場合などに`output_dir`の外を消さないようにする
+        let relative = Path::new(slug);
+        if slug.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            continue;
+        }
+        let dir = config.output_dir.join(relative);
+        match fs::remove_file(dir.join("index.html")) {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        // `2024.md`を消しても`2024/rust.md`の出力は残すので、空になったディレクトリだけを消す
+        for dir in dir.ancestors() {
+            if dir == config.output_dir || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    data.retain(|data| !removed.contains(&data.slug));
+    manifest.pages.retain(|slug, _| !removed.contains(slug));
+    Ok(removed.into_iter().collect())
+}
+
+/// ページの入力のハッシュ
+fn page_hash(
+    markdown: &str,
+    templates: &[Vec<u8>],
+    data: &VecDeque<Data>,
+    slug: &str,
+    config: &Config,
+    strict: bool,
+    preview: bool,
+) -> String {
+    let entry = data
+        .iter()
+        .find(|data| data.slug == slug)
+        .map(|data| serde_json::to_vec(data).unwrap_or_default())
+        .unwrap_or_default();
+    let options = config.options();
+    let options = format!(
+        "excerpt_length={} drop_title_heading={}",
+        options.excerpt_length, options.drop_title_heading
+    );
+    // `$tags`のURLは`tag_slugs`で変わる
+    let tag_slugs = serde_json::to_vec(&config.tag_slugs).unwrap_or_default();
+    let mut parts = vec![
+        markdown.as_bytes(),
+        &entry,
+        options.as_bytes(),
+        &tag_slugs,
+        if strict { b"strict" } else { b"" },
+        if preview { b"preview" } else { b"" },
+    ];
+    parts.extend(templates.iter().map(Vec::as_slice));
+    hash(&parts)
+}
+
+/// 1記事を`data`(書き出す前の記事の一覧)の作成日と更新日で書き出し、
+/// 一覧の更新用の`Rendered`と公開状態と使われなかった変数を返す。
+/// 公開前の記事は`preview`でなければ書き出さず、前に書き出したものも消す
+fn build_page(
+    config: &Config,
+    template: &Template,
+    page: &Page,
+    data: &VecDeque<Data>,
+    strict: bool,
+    preview: bool,
+    now: DateTime<FixedOffset>,
+) -> Result<(Rendered, Status, Vec<String>)> {
+    let rendered = markdown_to_html(&page.markdown, &config.options())?;
+    let status = Status::of(&rendered, now)?;
+    let dir = config.output_dir.join(&page.slug);
+    if !status.is_published() && !preview {
+        match fs::remove_file(dir.join("index.html")) {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        return Ok((rendered, status, Vec::new()));
+    }
+
+    let mut context = rendered.to_context();
+    context.insert("date".to_string(), page.date.to_value());
+    context.insert("slug".to_string(), page.slug.to_value());
+    let updated_at = data::updated_at(
+        data,
+        &page.slug,
+        &data::content_hash(&rendered),
+        &now.format("%Y-%m-%d").to_string(),
+    );
+    context.insert("updated_at".to_string(), updated_at.to_value());
+    let tags = rendered
+        .front_matter
+        .get("tags")
+        .map(|tags| parse_tags(tags))
+        .unwrap_or_default()
+        .iter()
+        .map(|tag| tag_value(config, tag))
+        .collect::<Vec<_>>();
+    context.insert("tags".to_string(), tags.to_value());
+    let (html, unused) = if strict {
+        template.render_strict(&context)?
+    } else {
+        (template.render(&context), Vec::new())
+    };
+
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("index.html"), insert_banner(&html, &status))?;
+    Ok((rendered, status, unused))
+}
+
+/// `items`を`jobs`個のスレッドで`f`に渡し、結果を`items`の順に返す
+fn parallel_map<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..jobs.clamp(1, items.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            break;
+                        };
+                        results.push((i, f(item)));
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// `dir`以下の`.md`のファイル(パス順)
+pub fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|extension| extension == "md") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `content/2024/hello.md`なら`2024/hello`
+pub fn slug(content_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(content_dir)
+        .unwrap_or(path)
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        fs,
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime},
+    };
+
+    use super::{build, build_with_cache, parallel_map, slug};
+    use crate::{config::Config, data, error::Error, template::cache::TemplateCache};
+
+    /// 一時ディレクトリのサイトの設定。テンプレートや記事は各テストで書く
+    fn site(name: &str) -> (PathBuf, Config) {
+        let dir = std::env::temp_dir().join(format!("blog-parser-{}-{}", name, std::process::id()));
+        let config = Config {
+            content_dir: dir.join("content"),
+            template: dir.join("post.html"),
+            data: dir.join("data.json"),
+            output_dir: dir.join("public"),
+            manifest: dir.join("manifest.json"),
+            index_template: dir.join("index.html"),
+            tag_template: dir.join("tag.html"),
+            tag_cloud_template: dir.join("tags.html"),
+            ..Config::default()
+        };
+        fs::create_dir_all(&config.content_dir).unwrap();
+        (dir, config)
+    }
+
+    #[test]
+    fn test_build() {
+        let (dir, config) = site("build");
+        let config = Config {
+            jobs: 2,
+            per_page: 1,
+            ..config
+        };
+        fs::create_dir_all(config.content_dir.join("2024")).unwrap();
+        fs::write(
+            &config.template,
+            "<title>{{ $title }}</title>{% for tag in $tags %}{{ $tag.url }}{% endfor %}{{ $content | safe }}",
+        )
+        .unwrap();
+        fs::write(
+            config.content_dir.join("hello.md"),
+            "---\n---\n# こんにちは\n本文\n",
+        )
+        .unwrap();
+        fs::write(
+            config.content_dir.join("2024/rust.md"),
+            "---\ntitle: Rust\ntags: Rust, 入門\n---\n段落\n",
+        )
+        .unwrap();
+        fs::write(config.content_dir.join("broken.md"), "---\n---\n段落\n").unwrap();
+        fs::write(config.content_dir.join("memo.txt"), "無視される").unwrap();
+        fs::write(
+            &config.tag_template,
+            "{{ $tag.name }}:{% for post in $posts %} {{ $post.title }}{% endfor %}",
+        )
+        .unwrap();
+        fs::write(
+            &config.tag_cloud_template,
+            "{% for tag in $tags %}{{ $tag.name }}={{ $tag.count }} {% endfor %}",
+        )
+        .unwrap();
+        fs::write(
+            &config.index_template,
+            "{% for post in $posts %}<a href=\"{{ $post.url }}\">{{ $post.title }}</a>{% endfor %}",
+        )
+        .unwrap();
+
+        let summary = build(&config, false, false).unwrap();
+        assert_eq!(summary.built, ["2024/rust", "hello"]);
+        assert!(summary.warnings.is_empty());
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.errors[0].0, config.content_dir.join("broken.md"));
+        assert!(matches!(summary.errors[0].1, Error::TitleNotFound));
+
+        assert_eq!(
+            fs::read_to_string(config.output_dir.join("hello/index.html")).unwrap(),
+            "<title>こんにちは</title><h1>こんにちは</h1>\n<p>本文</p>\n"
+        );
+        assert_eq!(
+            fs::read_to_string(config.output_dir.join("2024/rust/index.html")).unwrap(),
+            "<title>Rust</title>/tags/Rust//tags/%E5%85%A5%E9%96%80/<p>段落</p>\n"
+        );
+        let data = data::read(&config.data).unwrap();
+        assert_eq!(
+            data.iter()
+                .map(|data| data.slug.as_str())
+                .collect::<Vec<_>>(),
+            ["hello", "2024/rust"]
+        );
+        assert_eq!(summary.index_pages, 2);
+        assert_eq!(summary.tag_pages, 2);
+        assert_eq!(
+            fs::read_to_string(config.output_dir.join("tags/入門/index.html")).unwrap(),
+            "入門: Rust"
+        );
+        assert_eq!(
+            fs::read_to_string(config.output_dir.join("tags/index.html")).unwrap(),
+            "Rust=1 入門=1 "
+        );
+        assert_eq!(
+            fs::read_to_string(config.output_dir.join("index.html")).unwrap(),
+            "<a href=\"/hello/\">こんにちは</a>"
+        );
+        assert_eq!(
+            fs::read_to_string(config.output_dir.join("page/2/index.html")).unwrap(),
+            "<a href=\"/2024/rust/\">Rust</a>"
+        );
+
+        // 変更がなければエラーの記事だけを読み直す
+        let summary = build(&config, false, false).unwrap();
+        assert!(summary.built.is_empty());
+        assert_eq!(summary.skipped, ["2024/rust", "hello"]);
+        assert_eq!(summary.errors.len(), 1);
+
+        fs::write(
+            config.content_dir.join("hello.md"),
+            "---\n---\n# こんにちは\n更新\n",
+        )
+        .unwrap();
+        fs::remove_file(config.output_dir.join("2024/rust/index.html")).unwrap();
+        let summary = build(&config, false, false).unwrap();
+        assert_eq!(summary.built, ["2024/rust", "hello"]);
+        assert!(summary.skipped.is_empty());
+
+        fs::write(config.content_dir.join("broken.md"), "---\n---\n# 直した\n").unwrap();
+        let summary = build(&config, false, false).unwrap();
+        assert_eq!(summary.built, ["broken"]);
+        assert!(summary.errors.is_empty());
+
+        // テンプレートが変わったらすべて書き出し直す
+        fs::write(&config.template, "{{ $title }}").unwrap();
+        let summary = build(&config, true, false).unwrap();
+        assert_eq!(summary.built.len(), 3);
+        assert!(summary
+            .warnings
+            .iter()
+            .any(|(path, name)| path.ends_with("hello.md") && name == "excerpt"));
+
+        // markdownを消したら一覧と出力からも消す
+        fs::remove_file(config.content_dir.join("2024/rust.md")).unwrap();
+        let summary = build(&config, false, false).unwrap();
+        assert_eq!(summary.removed, ["2024/rust"]);
+        assert!(!config.output_dir.join("2024").exists());
+        assert!(config.output_dir.join("hello/index.html").exists());
+        assert_eq!(
+            data::read(&config.data)
+                .unwrap()
+                .iter()
+                .map(|data| data.slug.as_str())
+                .collect::<Vec<_>>(),
+            ["broken", "hello"]
+        );
+        assert!(!fs::read_to_string(&config.manifest)
+            .unwrap()
+            .contains("2024/rust"));
+        assert!(!config.output_dir.join("tags/Rust").exists());
+        let summary = build(&config, false, false).unwrap();
+        assert!(summary.removed.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_options() {
+        let (dir, mut config) = site("options");
+        fs::write(&config.template, "{{ $content | safe }}").unwrap();
+        fs::write(
+            config.content_dir.join("a.md"),
+            "---\ntags: 入門\n---\n# 見出し\n段落\n",
+        )
+        .unwrap();
+        let html = || fs::read_to_string(config.output_dir.join("a/index.html")).unwrap();
+
+        build(&config, false, false).unwrap();
+        assert!(html().contains("見出し"));
+
+        // 設定が変わったら入力が同じでも書き出し直す
+        config.drop_title_heading = true;
+        let summary = build(&config, false, false).unwrap();
+        assert_eq!(summary.built, ["a"]);
+        assert!(!html().contains("見出し"));
+        assert!(html().contains("段落"));
+
+        fs::write(&config.template, "{{ $excerpt }}").unwrap();
+        config.excerpt_length = 1;
+        build(&config, false, false).unwrap();
+        assert_eq!(html(), "段…");
+
+        // タグのURLが変わったら書き出し直す
+        fs::write(
+            &config.template,
+            "{% for tag in $tags %}{{ $tag.url }}{% endfor %}",
+        )
+        .unwrap();
+        build(&config, false, false).unwrap();
+        assert_eq!(html(), "/tags/%E5%85%A5%E9%96%80/");
+        config.tag_slugs = BTreeMap::from([("入門".to_string(), "nyumon".to_string())]);
+        let summary = build(&config, false, false).unwrap();
+        assert_eq!(summary.built, ["a"]);
+        assert_eq!(html(), "/tags/nyumon/");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_with_cache() {
+        let (dir, config) = site("cache-build");
+        fs::write(&config.template, "<h1>{{ $title }}</h1>").unwrap();
+        fs::write(&config.index_template, "{{ $total_pages }}").unwrap();
+        fs::write(config.content_dir.join("a.md"), "# 見出し\n段落\n").unwrap();
+        let html = || fs::read_to_string(config.output_dir.join("a/index.html")).unwrap();
+
+        let mut cache = TemplateCache::new();
+        build_with_cache(&config, &mut cache, false, false).unwrap();
+        assert_eq!(html(), "<h1>見出し</h1>");
+
+        // 同じキャッシュでも、書き換えたテンプレートは読み直す
+        fs::write(&config.template, "<h2>{{ $title }}</h2>").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&config.template)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        let summary = build_with_cache(&config, &mut cache, false, false).unwrap();
+        assert_eq!(summary.built, ["a"]);
+        assert_eq!(summary.index_pages, 1);
+        assert_eq!(html(), "<h2>見出し</h2>");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_unpublished() {
+        let (dir, config) = site("unpublished");
+        fs::write(&config.template, "<body>{{ $content | safe }}</body>").unwrap();
+        fs::write(
+            &config.index_template,
+            "{% for post in $posts %}{{ $post.slug }} {% endfor %}",
+        )
+        .unwrap();
+        fs::write(
+            config.content_dir.join("draft.md"),
+            "---\ntitle: 下書き\ndraft: true\n---\n段落\n",
+        )
+        .unwrap();
+        fs::write(
+            config.content_dir.join("scheduled.md"),
+            "---\ntitle: 予約\npublish_at: 2999-01-01\n---\n段落\n",
+        )
+        .unwrap();
+        fs::write(
+            config.content_dir.join("published.md"),
+            "---\ntitle: 公開\npublish_at: 2000-01-01T09:00:00+09:00\n---\n段落\n",
+        )
+        .unwrap();
+        let slugs = || {
+            data::read(&config.data)
+                .unwrap()
+                .iter()
+                .map(|data| data.slug.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let summary = build(&config, false, false).unwrap();
+        assert_eq!(summary.built, ["published"]);
+        assert_eq!(summary.unpublished, ["draft", "scheduled"]);
+        assert!(!config.output_dir.join("draft/index.html").exists());
+        assert!(!config.output_dir.join("scheduled/index.html").exists());
+        assert_eq!(slugs(), ["published"]);
+        assert_eq!(
+            fs::read_to_string(config.output_dir.join("index.html")).unwrap(),
+            "published "
+        );
+
+        // プレビューでは帯を付けて書き出すが、一覧には入れない
+        let summary = build(&config, false, true).unwrap();
+        assert_eq!(summary.built, ["draft", "published", "scheduled"]);
+        assert_eq!(summary.unpublished, ["draft", "scheduled"]);
+        let html = fs::read_to_string(config.output_dir.join("draft/index.html")).unwrap();
+        assert!(html.starts_with("<body><div class=\"preview-banner\""));
+        assert!(
+            fs::read_to_string(config.output_dir.join("scheduled/index.html"))
+                .unwrap()
+                .contains("published at 2999-01-01 00:00")
+        );
+        assert!(
+            !fs::read_to_string(config.output_dir.join("published/index.html"))
+                .unwrap()
+                .contains("preview-banner")
+        );
+        assert_eq!(slugs(), ["published"]);
+
+        // 本番のビルドに戻すとプレビューで書き出したページは消える
+        build(&config, false, false).unwrap();
+        assert!(!config.output_dir.join("draft/index.html").exists());
+
+        fs::write(
+            config.content_dir.join("draft.md"),
+            "---\ntitle: 下書き\n---\n段落\n",
+        )
+        .unwrap();
+        let summary = build(&config, false, false).unwrap();
+        assert_eq!(summary.built, ["draft"]);
+        assert_eq!(summary.unpublished, ["scheduled"]);
+        assert_eq!(slugs(), ["draft", "published"]);
+
+        fs::write(
+            config.content_dir.join("draft.md"),
+            "---\ntitle: 下書き\ndraft: true\n---\n段落\n",
+        )
+        .unwrap();
+        build(&config, false, false).unwrap();
+        assert_eq!(slugs(), ["published"]);
+        assert!(!config.output_dir.join("draft/index.html").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parallel_map() {
+        let items = (0..100).collect::<Vec<_>>();
+        for jobs in [0, 1, 4, 200] {
+            assert_eq!(
+                parallel_map(&items, jobs, |i| i * 2),
+                items.iter().map(|i| i * 2).collect::<Vec<_>>()
+            );
+        }
+        assert!(parallel_map(&Vec::<usize>::new(), 4, |i| *i).is_empty());
+    }
+
+    #[test]
+    fn test_slug() {
+        let content = Path::new("content");
+        assert_eq!(slug(content, Path::new("content/hello.md")), "hello");
+        assert_eq!(
+            slug(content, Path::new("content/2024/rust.md")),
+            "2024/rust"
+        );
+    }
+}
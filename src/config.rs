@@ -0,0 +1,190 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::Result, Options};
+
+/// `build`などサイト全体を扱うコマンドの設定。パスは実行したディレクトリからの相対パス
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 記事のmarkdownを置くディレクトリ
+    pub content_dir: PathBuf,
+    /// 記事のテンプレート
+    pub template: PathBuf,
+    /// 記事の一覧のJSON
+    pub data: PathBuf,
+    /// 記事の一覧のページのテンプレート。ファイルがなければ一覧は書き出さない
+    pub index_template: PathBuf,
+    /// 一覧の1ページあたりの記事数
+    pub per_page: usize,
+    /// `<slug>/index.html`を書き出すディレクトリ
+    pub output_dir: PathBuf,
+    /// 差分ビルド用に、前回のビルドの入力のハッシュを保存するファイル
+    pub manifest: PathBuf,
+    /// 記事を並列に書き出すスレッド数。0ならCPUの数
+    pub jobs: usize,
+    /// `serve`で使う`localhost`のポート
+    pub port: u16,
+    /// 公開するサイトのURL(`https://example.com/blog/`など)。空ならフィードを書き出さない
+    pub base_url: String,
+    pub site_title: String,
+    pub site_description: String,
+    /// Atomの`author`。空なら`site_title`
+    pub author: String,
+    /// フィードに入れる新しい記事の数
+    pub feed_limit: usize,
+    /// フィードに記事のHTMLも入れるかどうか
+    pub feed_full_content: bool,
+    /// `robots.txt`の内容。最後に`sitemap.xml`の場所を足す
+    pub robots_txt: String,
+    /// タグごとの記事の一覧(`tags/<tag>/index.html`)のテンプレート。ファイルがなければ書き出さない
+    pub tag_template: PathBuf,
+    /// タグの一覧と記事数(`tags/index.html`)のテンプレート。ファイルがなければ書き出さない
+    pub tag_cloud_template: PathBuf,
+    /// タグのURLに使う名前(`{ "入門": "nyumon" }`など)。なければタグをパーセントエンコードする
+    pub tag_slugs: BTreeMap<String, String>,
+    /// 記事の一覧に残す、それまでの更新日の数。0なら残さない
+    pub revision_limit: usize,
+    /// `excerpt`の最大文字数(書記素単位)
+    pub excerpt_length: usize,
+    /// タイトルを最初の見出しから取った場合に、その見出しを本文から取り除くかどうか
+    pub drop_title_heading: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            content_dir: PathBuf::from("content"),
+            template: PathBuf::from("templates/post.html"),
+            data: PathBuf::from("data.json"),
+            index_template: PathBuf::from("templates/index.html"),
+            per_page: 10,
+            output_dir: PathBuf::from("public"),
+            manifest: PathBuf::from(".build-manifest.json"),
+            jobs: 0,
+            port: 8000,
+            base_url: String::new(),
+            site_title: String::new(),
+            site_description: String::new(),
+            author: String::new(),
+            feed_limit: 20,
+            feed_full_content: false,
+            robots_txt: "User-agent: *\nAllow: /\n".to_string(),
+            tag_template: PathBuf::from("templates/tag.html"),
+            tag_cloud_template: PathBuf::from("templates/tags.html"),
+            tag_slugs: BTreeMap::new(),
+            revision_limit: 5,
+            excerpt_length: 120,
+            drop_title_heading: false,
+        }
+    }
+}
+
+impl Config {
+    /// 実際に使うスレッド数
+    pub fn jobs(&self) -> usize {
+        match self.jobs {
+            0 => thread::available_parallelism().map_or(1, usize::from),
+            jobs => jobs,
+        }
+    }
+
+    /// 記事を描画するときのオプション
+    pub fn options(&self) -> Options {
+        Options {
+            excerpt_length: self.excerpt_length,
+            drop_title_heading: self.drop_title_heading,
+            ..Options::default()
+        }
+    }
+
+    /// `watch`で監視するディレクトリ。記事と、すべてのテンプレートのディレクトリ
+    pub fn watch_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.content_dir.clone()];
+        for template in [
+            &self.template,
+            &self.index_template,
+            &self.tag_template,
+            &self.tag_cloud_template,
+        ] {
+            // `post.html`の親は空のパスなので、実行したディレクトリにする
+            let dir = match template.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs
+    }
+
+    /// `path`のJSONを読む。ファイルがなければ既定値、書かれていない項目も既定値になる
+    pub fn load(path: impl AsRef<Path>) -> Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::Config;
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("blog-parser-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("blog.json");
+
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+
+        fs::write(&path, r#"{ "content_dir": "posts", "output_dir": "dist" }"#).unwrap();
+        assert_eq!(
+            Config::load(&path).unwrap(),
+            Config {
+                content_dir: PathBuf::from("posts"),
+                output_dir: PathBuf::from("dist"),
+                ..Config::default()
+            }
+        );
+
+        fs::write(&path, "{").unwrap();
+        assert!(Config::load(&path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_watch_dirs() {
+        assert_eq!(
+            Config::default().watch_dirs(),
+            [PathBuf::from("content"), PathBuf::from("templates")]
+        );
+        let config = Config {
+            template: PathBuf::from("post.html"),
+            index_template: PathBuf::from("./index.html"),
+            tag_template: PathBuf::from("layouts/tag.html"),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.watch_dirs(),
+            [
+                PathBuf::from("content"),
+                PathBuf::from("."),
+                PathBuf::from("layouts"),
+                PathBuf::from("templates"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    manifest::hash,
+    tags::parse_tags,
+    template::{filter::url_encode, ToValue, Value},
+    Rendered,
+};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Data {
+    pub slug: String,
+    pub title: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub reading_time: usize,
+    #[serde(default)]
+    pub char_count: usize,
+    /// front matterの`tags: Rust, 入門`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 本文が最後に変わった日。空なら作成日
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub updated_at: String,
+    /// それまでの更新日(新しい順、`revision_limit`件まで)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revisions: Vec<String>,
+    /// 本文が変わったかどうかを調べるための、描画した本文のハッシュ
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub content_hash: String,
+}
+
+impl Data {
+    /// 更新日。本文が変わったことがなければ作成日
+    pub fn updated_at(&self) -> &str {
+        if self.updated_at.is_empty() {
+            &self.created_at
+        } else {
+            &self.updated_at
+        }
+    }
+}
+
+/// 記事のURLのパス。`2024/rust`のような`/`区切りの部分をそれぞれエンコードする
+pub fn post_path(slug: &str) -> String {
+    let segments = slug.split('/').map(url_encode).collect::<Vec<_>>();
+    format!("/{}/", segments.join("/"))
+}
+
+impl ToValue for Data {
+    /// 一覧のテンプレートで使う`$post.title`などと、記事のURLの`$post.url`。
+    /// `$post.tags`はタグの名前、`$post.revisions`はそれまでの更新日
+    fn to_value(&self) -> Value {
+        let mut map = [
+            ("slug", self.slug.clone()),
+            ("title", self.title.clone()),
+            ("created_at", self.created_at.clone()),
+            ("updated_at", self.updated_at().to_string()),
+            ("reading_time", self.reading_time.to_string()),
+            ("char_count", self.char_count.to_string()),
+            ("url", post_path(&self.slug)),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), Value::String(value)))
+        .collect::<BTreeMap<_, _>>();
+        map.insert("tags".to_string(), self.tags.to_value());
+        map.insert("revisions".to_string(), self.revisions.to_value());
+        Value::Map(map)
+    }
+}
+
+pub fn read(path: impl AsRef<Path>) -> Result<VecDeque<Data>> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+pub fn write(path: impl AsRef<Path>, data: &VecDeque<Data>) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(data)?)?;
+    Ok(())
+}
+
+/// 新しい記事の作成日にする今日の日付
+pub fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// 一覧にある`slug`の記事の作成日
+pub fn created_at<'a>(data: &'a VecDeque<Data>, slug: &str) -> Option<&'a str> {
+    data.iter()
+        .find(|data| data.slug == slug)
+        .map(|data| data.created_at.as_str())
+}
+
+/// 描画した本文のハッシュ
+pub fn content_hash(rendered: &Rendered) -> String {
+    hash(&[rendered.content.as_bytes()])
+}
+
+/// 本文のハッシュが`content_hash`の`slug`の記事の更新日。
+/// 本文が前と同じなら前の更新日、変わったか新しい記事なら`today`
+pub fn updated_at(data: &VecDeque<Data>, slug: &str, content_hash: &str, today: &str) -> String {
+    match data.iter().find(|data| data.slug == slug) {
+        // ハッシュを記録する前の記事は、変わったかどうかわからないので更新日を変えない
+        Some(article)
+            if article.content_hash.is_empty() || article.content_hash == content_hash =>
+        {
+            article.updated_at().to_string()
+        }
+        _ => today.to_string(),
+    }
+}
+
+/// `slug`の記事のタイトルなどを更新し(なければ`today`を作成日として先頭に追加し)、その記事の作成日を返す。
+/// 本文が変わっていれば更新日を`today`にし、前の更新日を`revision_limit`件まで残す
+pub fn upsert(
+    data: &mut VecDeque<Data>,
+    slug: &str,
+    rendered: &Rendered,
+    today: &str,
+    revision_limit: usize,
+) -> String {
+    let tags = rendered
+        .front_matter
+        .get("tags")
+        .map(|tags| parse_tags(tags))
+        .unwrap_or_default();
+    let content_hash = content_hash(rendered);
+    let updated_at = updated_at(data, slug, &content_hash, today);
+    if let Some(article) = data.iter_mut().find(|data| data.slug == slug) {
+        article.title = rendered.title.clone();
+        article.reading_time = rendered.reading_time;
+        article.char_count = rendered.char_count;
+        article.tags = tags;
+        if updated_at != article.updated_at() {
+            let previous = article.updated_at().to_string();
+            article.revisions.insert(0, previous);
+            article.revisions.truncate(revision_limit);
+            article.updated_at = updated_at;
+        }
+        article.content_hash = content_hash;
+        article.created_at.clone()
+    } else {
+        data.push_front(Data {
+            slug: slug.to_string(),
+            title: rendered.title.clone(),
+            created_at: today.to_string(),
+            reading_time: rendered.reading_time,
+            char_count: rendered.char_count,
+            tags,
+            updated_at,
+            revisions: Vec::new(),
+            content_hash,
+        });
+        today.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::{post_path, upsert, Data};
+    use crate::{
+        markdown_to_html,
+        template::{ToValue, Value},
+        Options,
+    };
+
+    #[test]
+    fn test_post_path() {
+        assert_eq!(post_path("hello"), "/hello/");
+        assert_eq!(post_path("2024/a b&c"), "/2024/a%20b%26c/");
+        let data = Data {
+            slug: "2024/ラスト".to_string(),
+            ..Data::default()
+        };
+        let Value::Map(map) = data.to_value() else {
+            panic!("not a map");
+        };
+        assert_eq!(
+            map["url"],
+            Value::String("/2024/%E3%83%A9%E3%82%B9%E3%83%88/".to_string())
+        );
+    }
+
+    #[test]
+    fn test_upsert() {
+        let rendered = |body: &str| {
+            markdown_to_html(
+                &format!("---\ntitle: タイトル\n---\n{}\n", body),
+                &Options::default(),
+            )
+            .unwrap()
+        };
+        let mut data = VecDeque::new();
+        let updates = |data: &VecDeque<Data>| {
+            (
+                data[0].created_at.clone(),
+                data[0].updated_at().to_string(),
+                data[0].revisions.clone(),
+            )
+        };
+
+        assert_eq!(
+            upsert(&mut data, "a", &rendered("本文"), "2024-04-01", 2),
+            "2024-04-01"
+        );
+        assert_eq!(
+            updates(&data),
+            ("2024-04-01".to_string(), "2024-04-01".to_string(), vec![])
+        );
+
+        // 本文が同じなら更新日は変えない
+        upsert(&mut data, "a", &rendered("本文"), "2024-04-02", 2);
+        assert_eq!(updates(&data).1, "2024-04-01");
+
+        upsert(&mut data, "a", &rendered("更新"), "2024-04-03", 2);
+        upsert(&mut data, "a", &rendered("再更新"), "2024-04-03", 2);
+        assert_eq!(
+            updates(&data),
+            (
+                "2024-04-01".to_string(),
+                "2024-04-03".to_string(),
+                vec!["2024-04-01".to_string()]
+            )
+        );
+        upsert(&mut data, "a", &rendered("更新"), "2024-04-04", 2);
+        upsert(&mut data, "a", &rendered("本文"), "2024-04-05", 2);
+        assert_eq!(
+            updates(&data).2,
+            ["2024-04-04".to_string(), "2024-04-03".to_string()]
+        );
+
+        // ハッシュを記録する前の記事は次から比べる
+        data[0].content_hash.clear();
+        upsert(&mut data, "a", &rendered("変更"), "2024-04-06", 2);
+        assert_eq!(updates(&data).1, "2024-04-05");
+        assert!(!data[0].content_hash.is_empty());
+    }
+}
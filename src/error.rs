@@ -0,0 +1,119 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    TitleNotFound,
+    /// 先頭の`---`に対応する`---`がない
+    FrontMatterNotClosed,
+    /// タグのslugが`tags/`の下の1つのディレクトリ名にならない
+    InvalidTagSlug {
+        tag: String,
+        slug: String,
+    },
+    /// 2つのタグが同じディレクトリに書き出される
+    DuplicateTagSlug {
+        tags: (String, String),
+        slug: String,
+    },
+    /// front matterの`publish_at`が日付でも日時でもない
+    InvalidPublishAt(String),
+    /// 下書きか公開前の記事を`--preview`なしで出力しようとした
+    Unpublished {
+        slug: String,
+    },
+    Template {
+        /// `Template::parse`で文字列から読んだ場合は`None`
+        template: Option<String>,
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TitleNotFound => write!(
+                f,
+                "title not found: add `title:` to the front matter or a heading to the markdown"
+            ),
+            Self::FrontMatterNotClosed => write!(
+                f,
+                "front matter is not closed: add `---` after the front matter"
+            ),
+            Self::InvalidTagSlug { tag, slug } => write!(
+                f,
+                "invalid slug `{}` for tag `{}`: set a single directory name in `tag_slugs`",
+                slug, tag
+            ),
+            Self::DuplicateTagSlug {
+                tags: (first, second),
+                slug,
+            } => write!(
+                f,
+                "tags `{}` and `{}` both use the slug `{}`: set different names in `tag_slugs`",
+                first, second, slug
+            ),
+            Self::InvalidPublishAt(value) => write!(
+                f,
+                "invalid `publish_at: {}`: use a date like `2024-04-01` or an RFC 3339 date and time",
+                value
+            ),
+            Self::Unpublished { slug } => write!(
+                f,
+                "`{}` is not published yet: use --preview to render it",
+                slug
+            ),
+            Self::Template {
+                template: Some(template),
+                message,
+                line,
+                column,
+            } => write!(
+                f,
+                "template error at {}:{}:{}: {}",
+                template, line, column, message
+            ),
+            Self::Template {
+                template: None,
+                message,
+                line,
+                column,
+            } => write!(f, "template error at {}:{}: {}", line, column, message),
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Json(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::TitleNotFound
+            | Self::FrontMatterNotClosed
+            | Self::InvalidTagSlug { .. }
+            | Self::DuplicateTagSlug { .. }
+            | Self::InvalidPublishAt(_)
+            | Self::Unpublished { .. }
+            | Self::Template { .. } => None,
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Offset, TimeZone};
+
+use crate::{
+    config::Config,
+    data::{post_path, Data},
+    error::Result,
+    index::newest_first,
+    markdown_to_html,
+    template::filter::escape_html,
+};
+
+/// フィードの1記事
+pub struct Item<'a> {
+    pub data: &'a Data,
+    pub description: String,
+    /// 記事のHTML。`feed_full_content`が偽なら使わない
+    pub content: String,
+}
+
+/// `2024-04-01`(`offset`の0時とする)かRFC 3339の日時
+pub fn parse_date(text: &str, offset: FixedOffset) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(text).ok().or_else(|| {
+        let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+        offset
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .single()
+    })
+}
+
+/// `base_url`と`slug`からの記事の絶対URL
+pub fn post_url(base_url: &str, slug: &str) -> String {
+    format!("{}{}", base_url.trim_end_matches('/'), post_path(slug))
+}
+
+/// `]]>`を含んでいても壊れないCDATA
+fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// 記事のうちで一番新しい更新日時
+fn last_updated(items: &[Item], offset: FixedOffset) -> Option<DateTime<FixedOffset>> {
+    items
+        .iter()
+        .filter_map(|item| parse_date(item.data.updated_at(), offset))
+        .max()
+}
+
+/// RSS 2.0のフィード。日付はRFC 822で、`lastBuildDate`は記事の一番新しい更新日時
+pub fn rss(config: &Config, items: &[Item], offset: FixedOffset) -> String {
+    let base_url = config.base_url.trim_end_matches('/');
+    let date = |data: &Data| parse_date(&data.created_at, offset).map(|date| date.to_rfc2822());
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:content=\"http://purl.org/rss/1.0/modules/content/\">\n<channel>\n");
+    xml.push_str(&format!(
+        "<title>{}</title>\n<link>{}/</link>\n<description>{}</description>\n",
+        escape_html(&config.site_title),
+        escape_html(base_url),
+        escape_html(&config.site_description)
+    ));
+    xml.push_str(&format!(
+        "<atom:link href=\"{}/feed.xml\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        escape_html(base_url)
+    ));
+    if let Some(date) = last_updated(items, offset) {
+        xml.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>\n",
+            date.to_rfc2822()
+        ));
+    }
+    for item in items {
+        let url = escape_html(&post_url(base_url, &item.data.slug));
+        xml.push_str("<item>\n");
+        xml.push_str(&format!(
+            "<title>{}</title>\n<link>{}</link>\n<guid isPermaLink=\"true\">{}</guid>\n",
+            escape_html(&item.data.title),
+            url,
+            url
+        ));
+        if let Some(date) = date(item.data) {
+            xml.push_str(&format!("<pubDate>{}</pubDate>\n", date));
+        }
+        xml.push_str(&format!(
+            "<description>{}</description>\n",
+            cdata(&item.description)
+        ));
+        if config.feed_full_content {
+            xml.push_str(&format!(
+                "<content:encoded>{}</content:encoded>\n",
+                cdata(&item.content)
+            ));
+        }
+        xml.push_str("</item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Atomのフィード。日付はRFC 3339で、`updated`は記事の更新日時
+pub fn atom(config: &Config, items: &[Item], offset: FixedOffset) -> String {
+    let base_url = config.base_url.trim_end_matches('/');
+    let date = |date: &str| parse_date(date, offset).map(|date| date.to_rfc3339());
+    let author = if config.author.is_empty() {
+        &config.site_title
+    } else {
+        &config.author
+    };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!(
+        "<title>{}</title>\n<link href=\"{}/\"/>\n<link href=\"{}/atom.xml\" rel=\"self\"/>\n<id>{}/</id>\n",
+        escape_html(&config.site_title),
+        escape_html(base_url),
+        escape_html(base_url),
+        escape_html(base_url)
+    ));
+    // 記事がなくても`updated`は必須
+    let updated = last_updated(items, offset)
+        .unwrap_or_else(|| Local::now().with_timezone(&offset))
+        .to_rfc3339();
+    xml.push_str(&format!(
+        "<updated>{}</updated>\n<author><name>{}</name></author>\n",
+        updated,
+        escape_html(author)
+    ));
+    for item in items {
+        let url = escape_html(&post_url(base_url, &item.data.slug));
+        xml.push_str("<entry>\n");
+        xml.push_str(&format!(
+            "<title>{}</title>\n<link href=\"{}\"/>\n<id>{}</id>\n",
+            escape_html(&item.data.title),
+            url,
+            url
+        ));
+        if let Some(published) = date(&item.data.created_at) {
+            xml.push_str(&format!("<published>{}</published>\n", published));
+        }
+        if let Some(updated) = date(item.data.updated_at()) {
+            xml.push_str(&format!("<updated>{}</updated>\n", updated));
+        }
+        xml.push_str(&format!(
+            "<summary type=\"text\">{}</summary>\n",
+            cdata(&item.description)
+        ));
+        if config.feed_full_content {
+            xml.push_str(&format!(
+                "<content type=\"html\">{}</content>\n",
+                cdata(&item.content)
+            ));
+        }
+        xml.push_str("</entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// 新しい`feed_limit`件の記事で`feed.xml`と`atom.xml`を書き出す。
+/// 説明と本文は`sources`(slugとmarkdown)から描画する。`base_url`がなければ何もしない
+pub fn write_feeds(
+    config: &Config,
+    data: &VecDeque<Data>,
+    sources: &HashMap<String, String>,
+) -> Result<bool> {
+    if config.base_url.is_empty() {
+        return Ok(false);
+    }
+    let items = newest_first(data)
+        .into_iter()
+        .take(config.feed_limit)
+        .map(|data| {
+            let rendered = sources
+                .get(&data.slug)
+                .and_then(|markdown| markdown_to_html(markdown, &config.options()).ok());
+            Item {
+                data,
+                description: rendered
+                    .as_ref()
+                    .map(|rendered| rendered.description.clone())
+                    .unwrap_or_default(),
+                content: rendered
+                    .map(|rendered| rendered.content)
+                    .unwrap_or_default(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let offset = Local::now().offset().fix();
+    fs::create_dir_all(&config.output_dir)?;
+    fs::write(
+        config.output_dir.join("feed.xml"),
+        rss(config, &items, offset),
+    )?;
+    fs::write(
+        config.output_dir.join("atom.xml"),
+        atom(config, &items, offset),
+    )?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::{atom, cdata, parse_date, post_url, rss, Item};
+    use crate::{config::Config, data::Data};
+
+    fn jst() -> FixedOffset {
+        FixedOffset::east_opt(9 * 60 * 60).unwrap()
+    }
+
+    fn config(full_content: bool) -> Config {
+        Config {
+            base_url: "https://example.com/blog/".to_string(),
+            site_title: "Rust & ブログ".to_string(),
+            site_description: "説明".to_string(),
+            feed_full_content: full_content,
+            ..Config::default()
+        }
+    }
+
+    fn data() -> Data {
+        Data {
+            slug: "2024/rust".to_string(),
+            title: "<Rust>入門".to_string(),
+            created_at: "2024-04-01".to_string(),
+            reading_time: 1,
+            char_count: 100,
+            ..Data::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2024-04-01", jst()).unwrap().to_rfc3339(),
+            "2024-04-01T00:00:00+09:00"
+        );
+        assert_eq!(
+            parse_date("2024-04-01T09:30:00Z", jst())
+                .unwrap()
+                .to_rfc2822(),
+            "Mon, 1 Apr 2024 09:30:00 +0000"
+        );
+        assert_eq!(parse_date("不明", jst()), None);
+    }
+
+    #[test]
+    fn test_post_url() {
+        assert_eq!(
+            post_url("https://example.com/blog/", "2024/rust"),
+            "https://example.com/blog/2024/rust/"
+        );
+        assert_eq!(
+            post_url("https://example.com", "2024/はじめて の?記事#1"),
+            "https://example.com/2024/%E3%81%AF%E3%81%98%E3%82%81%E3%81%A6%20%E3%81%AE%3F%E8%A8%98%E4%BA%8B%231/"
+        );
+    }
+
+    #[test]
+    fn test_cdata() {
+        assert_eq!(
+            cdata("<p>a]]>b</p>"),
+            "<![CDATA[<p>a]]]]><![CDATA[>b</p>]]>"
+        );
+    }
+
+    #[test]
+    fn test_rss() {
+        let data = data();
+        let items = [Item {
+            data: &data,
+            description: "記事の説明".to_string(),
+            content: "<p>本文</p>".to_string(),
+        }];
+        assert_eq!(
+            rss(&config(true), &items, jst()),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:content=\"http://purl.org/rss/1.0/modules/content/\">
+<channel>
+<title>Rust &amp; ブログ</title>
+<link>https://example.com/blog/</link>
+<description>説明</description>
+<atom:link href=\"https://example.com/blog/feed.xml\" rel=\"self\" type=\"application/rss+xml\"/>
+<lastBuildDate>Mon, 1 Apr 2024 00:00:00 +0900</lastBuildDate>
+<item>
+<title>&lt;Rust&gt;入門</title>
+<link>https://example.com/blog/2024/rust/</link>
+<guid isPermaLink=\"true\">https://example.com/blog/2024/rust/</guid>
+<pubDate>Mon, 1 Apr 2024 00:00:00 +0900</pubDate>
+<description><![CDATA[記事の説明]]></description>
+<content:encoded><![CDATA[<p>本文</p>]]></content:encoded>
+</item>
+</channel>
+</rss>
+"
+        );
+        assert!(!rss(&config(false), &items, jst()).contains("content:encoded>"));
+
+        let data = Data {
+            updated_at: "2024-04-10".to_string(),
+            ..data
+        };
+        let items = [Item {
+            data: &data,
+            description: String::new(),
+            content: String::new(),
+        }];
+        let xml = rss(&config(false), &items, jst());
+        assert!(xml.contains("<lastBuildDate>Wed, 10 Apr 2024 00:00:00 +0900</lastBuildDate>"));
+        assert!(xml.contains("<pubDate>Mon, 1 Apr 2024 00:00:00 +0900</pubDate>"));
+    }
+
+    #[test]
+    fn test_atom() {
+        let data = Data {
+            updated_at: "2024-04-10T12:00:00+09:00".to_string(),
+            ..data()
+        };
+        let items = [Item {
+            data: &data,
+            description: "記事の説明".to_string(),
+            content: "<p>本文</p>".to_string(),
+        }];
+        assert_eq!(
+            atom(&config(true), &items, jst()),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<feed xmlns=\"http://www.w3.org/2005/Atom\">
+<title>Rust &amp; ブログ</title>
+<link href=\"https://example.com/blog/\"/>
+<link href=\"https://example.com/blog/atom.xml\" rel=\"self\"/>
+<id>https://example.com/blog/</id>
+<updated>2024-04-10T12:00:00+09:00</updated>
+<author><name>Rust &amp; ブログ</name></author>
+<entry>
+<title>&lt;Rust&gt;入門</title>
+<link href=\"https://example.com/blog/2024/rust/\"/>
+<id>https://example.com/blog/2024/rust/</id>
+<published>2024-04-01T00:00:00+09:00</published>
+<updated>2024-04-10T12:00:00+09:00</updated>
+<summary type=\"text\"><![CDATA[記事の説明]]></summary>
+<content type=\"html\"><![CDATA[<p>本文</p>]]></content>
+</entry>
+</feed>
+"
+        );
+        assert!(atom(&config(false), &[], jst()).contains("<updated>"));
+    }
+}
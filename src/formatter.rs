@@ -0,0 +1,195 @@
+use crate::{
+    error::Result,
+    lexer::{Contents, Li, ListTypes, RootTags},
+    markdown_to_document, Options,
+};
+
+/// markdownを決まった書式に整形する。
+/// front matterとコードブロックは元のテキストをそのまま残す。`\r\n`の改行は`\r\n`のまま書き出す
+pub fn format(markdown: &str, options: &Options) -> Result<String> {
+    if markdown.contains("\r\n") {
+        let formatted = format(&markdown.replace("\r\n", "\n"), options)?;
+        return Ok(formatted.replace('\n', "\r\n"));
+    }
+    let document = markdown_to_document(markdown, options)?;
+
+    let mut output = String::new();
+    let skip_cnt = document.front_matter.skip_cnt;
+    if skip_cnt > 0 {
+        output.push_str(markdown.get(..skip_cnt).unwrap_or(markdown).trim_end());
+        output.push_str("\n\n");
+    }
+
+    let blocks = document
+        .tags
+        .iter()
+        .map(|tag| format_tag(tag, markdown))
+        .collect::<Vec<_>>();
+    output.push_str(&blocks.join("\n"));
+
+    if output.ends_with("\n\n") {
+        output.pop();
+    }
+    Ok(output)
+}
+
+fn format_tag(tag: &RootTags, markdown: &str) -> String {
+    match tag {
+        RootTags::H1(h1) => format!("# {}\n", h1.0),
+        RootTags::H2(h2) => format!("## {}\n", h2.0),
+        RootTags::H3(h3) => format!("### {}\n", h3.0),
+        RootTags::P(p) => format!("{}\n", format_contents(&p.0, markdown)),
+        RootTags::A(a) => format!("[{}]({})\n", a.text, a.href),
+        RootTags::Img(img) => format!("![{}]({})\n", img.alt, img.src),
+        RootTags::Li(lists) => {
+            let mut output = String::new();
+            let mut rest = &lists[..];
+            while !rest.is_empty() {
+                let consumed = format_list(rest, "", markdown, &mut output);
+                rest = &rest[consumed..];
+            }
+            output
+        }
+        RootTags::Pre(pre) => format!("{}\n", &markdown[pre.1.start..pre.1.end]),
+    }
+}
+
+/// 閉じていない`*`などを書き換えないように、行内の要素は元のテキストをそのまま使う
+fn format_contents(contents: &[Contents], markdown: &str) -> String {
+    contents
+        .iter()
+        .map(|content| {
+            let span = content.span();
+            &markdown[span.start..span.end]
+        })
+        .collect()
+}
+
+/// `lists[0]`と同じ階層に並ぶ項目を整形し、消費した項目数を返す。
+/// 番号付きリストは1から振り直し、子リストは親項目の本文の開始位置に揃える
+fn format_list(lists: &[Li], prefix: &str, markdown: &str, output: &mut String) -> usize {
+    let indent = lists[0].indent;
+    let mut number = 0;
+    let mut previous = None;
+    let mut i = 0;
+    while i < lists.len() && lists[i].indent == indent {
+        let li = &lists[i];
+        number = if previous == Some(li.list_type) {
+            number + 1
+        } else {
+            1
+        };
+        previous = Some(li.list_type);
+        let marker = match li.list_type {
+            ListTypes::Ul => "-".to_string(),
+            ListTypes::Ol => format!("{}.", number),
+        };
+        output.push_str(&format!(
+            "{}{} {}\n",
+            prefix,
+            marker,
+            format_contents(&li.contents, markdown)
+        ));
+
+        let child_prefix = format!("{}{}", prefix, " ".repeat(marker.len() + 1));
+        i += 1;
+        while i < lists.len() && lists[i].indent > indent {
+            i += format_list(&lists[i..], &child_prefix, markdown, output);
+        }
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{formatter::format, markdown_to_html, Options};
+
+    #[test]
+    fn test_format() {
+        let tests = [
+            ("#見出し1\n##   見出し2\n", "# 見出し1\n\n## 見出し2\n"),
+            (
+                "段落1\n段落2\n\n\n\n- リスト1\n\t- リスト1-1\n- **リスト2**\n",
+                "段落1\n\n段落2\n\n- リスト1\n  - リスト1-1\n- **リスト2**\n",
+            ),
+            (
+                "1. リスト1\n1. リスト2\n    1. リスト2-1\n    1. リスト2-2\n1. リスト3\n- リスト4\n",
+                "1. リスト1\n2. リスト2\n   1. リスト2-1\n   2. リスト2-2\n3. リスト3\n- リスト4\n",
+            ),
+            (
+                "段落\n```rust\nfn main() {\n\tprintln!(\"  \");\n}\n```\n\n\n",
+                "段落\n\n```rust\nfn main() {\n\tprintln!(\"  \");\n}\n```\n",
+            ),
+            (
+                "テキスト*イタリック*`コード`**ボールド**",
+                "テキスト*イタリック*`コード`**ボールド**\n",
+            ),
+            // 閉じていない記号は書き足さない
+            ("2 * 3 = 6\n- `a\n- **b\n", "2 * 3 = 6\n\n- `a\n- **b\n"),
+        ];
+
+        let options = Options {
+            front_matter: false,
+            ..Options::default()
+        };
+        for (input, output) in tests {
+            let formatted = format(input, &options).unwrap();
+            assert_eq!(formatted, output);
+            assert_eq!(format(&formatted, &options).unwrap(), formatted);
+        }
+    }
+
+    #[test]
+    fn test_format_without_front_matter() {
+        let formatted = format("#見出し1\n段落\n", &Options::default()).unwrap();
+        assert_eq!(formatted, "# 見出し1\n\n段落\n");
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let markdown = "---
+description: 説明
+---
+# 見出し1
+## 見出し2
+
+段落1
+
+```
+console.log('Hello, world!');
+```
+
+1. リスト1
+1. リスト2
+  1. リスト2-1
+    - リスト2-1-1
+  1. リスト2-2
+1. リスト3
+
+段落*2*
+";
+        let formatted = format(markdown, &Options::default()).unwrap();
+        assert!(formatted.starts_with("---\ndescription: 説明\n---\n\n# 見出し1\n\n## 見出し2\n"));
+        assert_eq!(format(&formatted, &Options::default()).unwrap(), formatted);
+        // 入れ子のインデント幅が変わるのでタブは比較しない
+        let html = |markdown: &str| {
+            markdown_to_html(markdown, &Options::default())
+                .unwrap()
+                .content
+                .replace('\t', "")
+        };
+        assert_eq!(html(&formatted), html(markdown));
+    }
+
+    #[test]
+    fn test_format_crlf() {
+        let markdown = "---\r\ntitle: 日本語のタイトル\r\n---\r\n# 見出し\r\n本文\r\n";
+        assert_eq!(
+            format(markdown, &Options::default()).unwrap(),
+            "---\r\ntitle: 日本語のタイトル\r\n---\r\n\r\n# 見出し\r\n\r\n本文\r\n"
+        );
+        let rendered = markdown_to_html(markdown, &Options::default()).unwrap();
+        assert_eq!(rendered.title, "日本語のタイトル");
+        assert!(rendered.content.starts_with("<h1>見出し"));
+    }
+}
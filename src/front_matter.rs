@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Params {
+    #[serde(default)]
+    pub title: String,
+    pub description: String,
+    /// `title`と`description`以外の`key: value`
+    #[serde(default, flatten)]
+    pub fields: BTreeMap<String, String>,
+    /// front matterのバイト数(本文の開始位置)
+    #[serde(skip)]
+    pub skip_cnt: usize,
+    /// front matterの行数
+    #[serde(skip)]
+    pub skip_lines: usize,
+}
+
+/// 先頭の行が`---`のときだけfront matterとして読む。閉じる`---`がなければエラー
+pub fn parse_params(markdown: &str) -> Result<Params> {
+    if !markdown
+        .lines()
+        .next()
+        .is_some_and(|line| line.starts_with("---"))
+    {
+        return Ok(Params::default());
+    }
+    let mut title = "";
+    let mut description = "";
+    let mut fields = BTreeMap::new();
+    let mut skip_cnt = 0;
+    let mut skip_lines = 0;
+    let mut cnt = 0;
+    // `\r\n`の行でも本文の開始位置がずれないように、改行を含めたバイト数を数える
+    for line in markdown.split_inclusive('\n') {
+        skip_cnt += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        skip_lines += 1;
+        if line.starts_with("---") {
+            cnt += 1;
+            if cnt == 2 {
+                break;
+            }
+        } else if line.starts_with("title:") {
+            title = line.trim_start_matches("title:").trim();
+        } else if line.starts_with("description:") {
+            description = line.trim_start_matches("description:").trim();
+        } else if let Some((key, value)) = line.split_once(':').filter(|_| cnt == 1) {
+            // テンプレートの変数名として使えるキーだけを読む
+            if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                fields.insert(key.to_string(), value.trim().to_string());
+            }
+        }
+    }
+    if cnt < 2 {
+        return Err(Error::FrontMatterNotClosed);
+    }
+    Ok(Params {
+        title: title.to_string(),
+        description: description.to_string(),
+        fields,
+        skip_cnt,
+        skip_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{parse_params, Params};
+    use crate::error::Error;
+
+    #[test]
+    fn test_parse_params() {
+        let markdown = "---\ndescription: 説明\n---\n# 見出し1\n";
+        assert_eq!(
+            parse_params(markdown).unwrap(),
+            Params {
+                title: String::new(),
+                description: "説明".to_string(),
+                fields: BTreeMap::new(),
+                skip_cnt: 28,
+                skip_lines: 3,
+            }
+        );
+        assert_eq!(&markdown[28..], "# 見出し1\n");
+
+        // `\r\n`の改行も含めて本文の開始位置を数える
+        let markdown = "---\r\ntitle: タイトル\r\n---\r\n# 見出し1\r\n";
+        let params = parse_params(markdown).unwrap();
+        assert_eq!(params.title, "タイトル");
+        assert_eq!(&markdown[params.skip_cnt..], "# 見出し1\r\n");
+
+        assert_eq!(
+            parse_params("---\ntitle: タイトル\n---\n").unwrap().title,
+            "タイトル"
+        );
+
+        assert_eq!(
+            parse_params("---\nauthor: 著者\ncreated_at: 2024-04-01T09:00:00+09:00\nno key\nbad-key: x\n---\nmemo: 本文\n")
+                .unwrap()
+                .fields,
+            BTreeMap::from([
+                ("author".to_string(), "著者".to_string()),
+                ("created_at".to_string(), "2024-04-01T09:00:00+09:00".to_string()),
+            ])
+        );
+
+        assert_eq!(
+            parse_params("---\n---\n# 見出し1\n").unwrap(),
+            Params {
+                title: String::new(),
+                description: String::new(),
+                fields: BTreeMap::new(),
+                skip_cnt: 8,
+                skip_lines: 2,
+            }
+        );
+
+        // 先頭が`---`でなければfront matterはない
+        assert_eq!(
+            parse_params(
+                "# 見出し1
+title: 本文
+---
+"
+            )
+            .unwrap(),
+            Params::default()
+        );
+        assert!(matches!(
+            parse_params(
+                "---
+title: タイトル
+# 見出し1
+"
+            ),
+            Err(Error::FrontMatterNotClosed)
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::Config,
+    data::Data,
+    error::Result,
+    tags::post_value,
+    template::{cache::TemplateCache, Context, ToValue},
+};
+
+/// 1ページ目は`/`、2ページ目以降は`/page/<n>/`
+pub fn page_url(page: usize) -> String {
+    match page {
+        1 => "/".to_string(),
+        page => format!("/page/{}/", page),
+    }
+}
+
+/// 作成日の新しい順の記事。同じ日の記事は一覧での順序(新しく追加したものが先)のまま
+pub fn newest_first(data: &VecDeque<Data>) -> Vec<&Data> {
+    let mut posts = data.iter().collect::<Vec<_>>();
+    posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    posts
+}
+
+/// 記事の一覧を作成日の新しい順に`per_page`件ずつに分けた、ページごとの出力先と変数。
+/// 記事がなくても1ページ目は作る
+pub fn paginate(config: &Config, data: &VecDeque<Data>) -> Vec<(PathBuf, Context)> {
+    let posts = newest_first(data)
+        .into_iter()
+        .map(|post| post_value(config, post))
+        .collect::<Vec<_>>();
+
+    let chunks = posts.chunks(config.per_page.max(1)).collect::<Vec<_>>();
+    let total_pages = chunks.len().max(1);
+    (1..=total_pages)
+        .map(|page| {
+            let posts = chunks.get(page - 1).copied().unwrap_or_default();
+            let path = match page {
+                1 => PathBuf::from("index.html"),
+                page => Path::new("page").join(page.to_string()).join("index.html"),
+            };
+            let neighbour = |page: usize| {
+                if (1..=total_pages).contains(&page) {
+                    page_url(page)
+                } else {
+                    String::new()
+                }
+            };
+
+            let mut context = Context::new();
+            context.insert("posts".to_string(), posts.to_value());
+            context.insert("page".to_string(), page.to_string().to_value());
+            context.insert(
+                "total_pages".to_string(),
+                total_pages.to_string().to_value(),
+            );
+            context.insert("prev_url".to_string(), neighbour(page - 1).to_value());
+            context.insert("next_url".to_string(), neighbour(page + 1).to_value());
+            (path, context)
+        })
+        .collect()
+}
+
+/// `index_template`で記事の一覧のページを書き出し、書き出したページ数を返す。
+/// テンプレートがなければ何もしない
+pub fn write_index(
+    config: &Config,
+    cache: &mut TemplateCache,
+    data: &VecDeque<Data>,
+) -> Result<usize> {
+    if !config.index_template.exists() {
+        return Ok(0);
+    }
+    let template = cache.get(&config.index_template)?;
+
+    let pages = paginate(config, data);
+    for (path, context) in &pages {
+        let path = config.output_dir.join(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, template.render(context))?;
+    }
+    remove_stale_pages(&config.output_dir, pages.len())?;
+    Ok(pages.len())
+}
+
+/// 記事が減ったときに古いページが残らないように、`total_pages`より後ろの`page/<n>/index.html`を消す。
+/// `content/page/about.md`のような記事の出力は消さない
+fn remove_stale_pages(output_dir: &Path, total_pages: usize) -> Result<()> {
+    let entries = match fs::read_dir(output_dir.join("page")) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let stale = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<usize>().ok())
+            .is_some_and(|page| page > total_pages);
+        if !stale {
+            continue;
+        }
+        match fs::remove_file(entry.path().join("index.html")) {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        // 同じディレクトリに他のファイルがあれば残す
+        let _ = fs::remove_dir(entry.path());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, fs, path::PathBuf};
+
+    use super::{paginate, write_index};
+    use crate::{
+        config::Config,
+        data::Data,
+        template::{cache::TemplateCache, Template, ToValue},
+    };
+
+    fn data(slug: &str, created_at: &str) -> Data {
+        Data {
+            slug: slug.to_string(),
+            title: slug.to_uppercase(),
+            created_at: created_at.to_string(),
+            reading_time: 1,
+            char_count: 100,
+            ..Data::default()
+        }
+    }
+
+    #[test]
+    fn test_paginate() {
+        let data = VecDeque::from([
+            data("b", "2024-01-02"),
+            data("a", "2024-01-01"),
+            data("d", "2024-03-01"),
+            data("c", "2024-01-02"),
+            data("e", "2023-12-31"),
+        ]);
+        let template = Template::parse(
+            "{{ $page }}/{{ $total_pages }}:\
+             {% for post in $posts %}<a href=\"{{ $post.url }}\">{{ $post.title }}</a>{% endfor %}\
+             {% if $prev_url %} prev={{ $prev_url }}{% endif %}\
+             {% if $next_url %} next={{ $next_url }}{% endif %}",
+        )
+        .unwrap();
+
+        let config = Config {
+            per_page: 2,
+            ..Config::default()
+        };
+        let pages = paginate(&config, &data)
+            .into_iter()
+            .map(|(path, context)| (path, template.render(&context)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pages,
+            [
+                (
+                    PathBuf::from("index.html"),
+                    "1/3:<a href=\"/d/\">D</a><a href=\"/b/\">B</a> next=/page/2/".to_string()
+                ),
+                (
+                    PathBuf::from("page/2/index.html"),
+                    "2/3:<a href=\"/c/\">C</a><a href=\"/a/\">A</a> prev=/ next=/page/3/"
+                        .to_string()
+                ),
+                (
+                    PathBuf::from("page/3/index.html"),
+                    "3/3:<a href=\"/e/\">E</a> prev=/page/2/".to_string()
+                ),
+            ]
+        );
+
+        let pages = paginate(&Config::default(), &VecDeque::new());
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].1["posts"], Vec::<String>::new().to_value());
+    }
+
+    #[test]
+    fn test_write_index() {
+        let dir = std::env::temp_dir().join(format!("blog-parser-index-{}", std::process::id()));
+        let config = Config {
+            index_template: dir.join("index.html"),
+            output_dir: dir.join("public"),
+            per_page: 1,
+            ..Config::default()
+        };
+        fs::create_dir_all(config.output_dir.join("page/about")).unwrap();
+        fs::write(&config.index_template, "{{ $page }}").unwrap();
+        // `content/page/about.md`の記事と前回の3ページ目
+        fs::write(config.output_dir.join("page/about/index.html"), "about").unwrap();
+        fs::create_dir_all(config.output_dir.join("page/3")).unwrap();
+        fs::write(config.output_dir.join("page/3/index.html"), "3").unwrap();
+
+        let data = VecDeque::from([data("a", "2024-01-01"), data("b", "2024-01-02")]);
+        assert_eq!(
+            write_index(&config, &mut TemplateCache::new(), &data).unwrap(),
+            2
+        );
+        let read = |path: &str| fs::read_to_string(config.output_dir.join(path)).ok();
+        assert_eq!(read("index.html").as_deref(), Some("1"));
+        assert_eq!(read("page/2/index.html").as_deref(), Some("2"));
+        assert_eq!(read("page/3/index.html"), None);
+        assert!(!config.output_dir.join("page/3").exists());
+        assert_eq!(read("page/about/index.html").as_deref(), Some("about"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum RootTags {
     H1(H1),
     H2(H2),
@@ -10,19 +12,49 @@ pub enum RootTags {
     Pre(Pre),
 }
 
-#[derive(Debug, PartialEq)]
-pub struct H1(pub String);
+impl RootTags {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::H1(h1) => h1.1,
+            Self::H2(h2) => h2.1,
+            Self::H3(h3) => h3.1,
+            Self::P(p) => p.1,
+            Self::A(a) => a.span,
+            Self::Img(img) => img.span,
+            Self::Li(lists) => match (lists.first(), lists.last()) {
+                (Some(first), Some(last)) => Span {
+                    end: last.span.end,
+                    ..first.span
+                },
+                _ => Span::default(),
+            },
+            Self::Pre(pre) => pre.1,
+        }
+    }
+}
+
+/// 元のmarkdown中のバイト範囲と開始位置の行・列(1始まり)
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct H1(pub String, pub Span);
 
-#[derive(Debug, PartialEq)]
-pub struct H2(pub String);
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct H2(pub String, pub Span);
 
-#[derive(Debug, PartialEq)]
-pub struct H3(pub String);
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct H3(pub String, pub Span);
 
-#[derive(Debug, PartialEq)]
-pub struct P(pub Vec<Contents>);
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct P(pub Vec<Contents>, pub Span);
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum Contents {
     Text(Text),
     Bold(Bold),
@@ -30,45 +62,59 @@ pub enum Contents {
     Code(Code),
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Text(pub String);
+impl Contents {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Text(text) => text.1,
+            Self::Bold(bold) => bold.1,
+            Self::Italic(italic) => italic.1,
+            Self::Code(code) => code.1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Text(pub String, pub Span);
 
-#[derive(Debug, PartialEq)]
-pub struct Bold(pub String);
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bold(pub String, pub Span);
 
-#[derive(Debug, PartialEq)]
-pub struct Italic(pub String);
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Italic(pub String, pub Span);
 
-#[derive(Debug, PartialEq)]
-pub struct Code(pub String);
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Code(pub String, pub Span);
 
-#[derive(Debug, PartialEq)]
-struct A {
-    href: String,
-    text: String,
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct A {
+    pub href: String,
+    pub text: String,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
-struct Img {
-    src: String,
-    alt: String,
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Img {
+    pub src: String,
+    pub alt: String,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Li {
     pub list_type: ListTypes,
     pub indent: usize,
     pub contents: Vec<Contents>,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum ListTypes {
     Ul,
     Ol,
 }
 
 impl ListTypes {
-    pub fn to_string(&self) -> &str {
+    pub fn to_string(self) -> &'static str {
         match self {
             Self::Ul => "ul",
             Self::Ol => "ol",
@@ -76,14 +122,17 @@ impl ListTypes {
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Pre(pub String);
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pre(pub String, pub Span);
 
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
     output: Vec<RootTags>,
     indent: usize,
+    offset: usize,
+    line: usize,
+    column: usize,
 }
 
 impl Lexer {
@@ -91,14 +140,17 @@ impl Lexer {
         self.input[self.position]
     }
 
-    fn peek_char(&self) -> Option<char> {
-        if self.position + 1 >= self.input.len() {
-            return None;
-        }
-        Some(self.input[self.position + 1])
-    }
-
     fn next_char(&mut self) -> Option<char> {
+        if self.position < self.input.len() {
+            let c = self.current_char();
+            self.offset += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
         self.position += 1;
         if self.position >= self.input.len() {
             return None;
@@ -127,75 +179,123 @@ impl Lexer {
         }
         text
     }
+
+    /// 現在位置から始まる長さ0のSpan
+    fn start_span(&self) -> Span {
+        Span {
+            start: self.offset,
+            end: self.offset,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// `span`の終わりを現在位置まで伸ばす
+    fn close_span(&self, span: Span) -> Span {
+        Span {
+            end: self.offset,
+            ..span
+        }
+    }
 }
 
-pub fn tokenize(input: &str) -> Vec<RootTags> {
-    let mut lexer = Lexer {
-        input: input.chars().collect(),
-        position: 0,
-        output: Vec::new(),
-        indent: 0,
-    };
+impl Lexer {
+    pub fn new(input: &str) -> Self {
+        Self::with_offset(input, 0, 1)
+    }
 
-    while lexer.position < lexer.input.len() {
-        let c = lexer.current_char();
-        match c {
-            '#' => tokenize_heading(&mut lexer),
+    /// `input`が元のmarkdownの`offset`バイト目・`line`行目から始まるものとして読む
+    /// (front matterを読み飛ばした本文のSpanを元のファイル上の位置に合わせるため)
+    pub fn with_offset(input: &str, offset: usize, line: usize) -> Self {
+        Lexer {
+            input: input.chars().collect(),
+            position: 0,
+            output: Vec::new(),
+            indent: 0,
+            offset,
+            line,
+            column: 1,
+        }
+    }
 
-            '-' => tokenize_list(&mut lexer),
+    pub fn tokenize(mut self) -> Vec<RootTags> {
+        while self.position < self.input.len() {
+            let c = self.current_char();
+            match c {
+                '#' => tokenize_heading(&mut self),
 
-            '1' => tokenize_ordered_list(&mut lexer),
-            '\t' | ' ' => {
-                lexer.indent += 1;
-                lexer.next_char();
-            }
-            '\n' => {
-                lexer.indent = 0;
-                lexer.next_char();
-            }
-            _ => {
-                let text = lexer.read_to_eol();
-                if text.starts_with("```") {
-                    tokenize_codeblock(&mut lexer);
-                } else {
-                    let p_contents = tokenize_contents(&text);
-                    lexer.output.push(RootTags::P(P(p_contents)));
+                '-' => tokenize_list(&mut self),
+
+                '0'..='9' => tokenize_ordered_list(&mut self),
+                '\t' | ' ' => {
+                    self.indent += 1;
+                    self.next_char();
+                }
+                '\n' => {
+                    self.indent = 0;
+                    self.next_char();
+                }
+                _ => {
+                    let span = self.start_span();
+                    let text = self.read_to_eol();
+                    if text.starts_with("```") {
+                        tokenize_codeblock(&mut self, span);
+                    } else {
+                        let p_contents = tokenize_contents(&text, span);
+                        self.output
+                            .push(RootTags::P(P(p_contents, self.close_span(span))));
+                    }
                 }
             }
         }
+
+        self.output
     }
+}
 
-    lexer.output
+pub fn tokenize(input: &str) -> Vec<RootTags> {
+    Lexer::new(input).tokenize()
+}
+
+pub fn tokenize_from(input: &str, offset: usize, line: usize) -> Vec<RootTags> {
+    Lexer::with_offset(input, offset, line).tokenize()
 }
 
 fn tokenize_heading(lexer: &mut Lexer) {
+    let span = lexer.start_span();
     if Some('#') == lexer.next_char() {
         if Some('#') == lexer.next_char() {
             lexer.next_char();
             lexer.skip_whitespace();
             let text = lexer.read_to_eol();
-            lexer.output.push(RootTags::H3(H3(text)));
+            let span = lexer.close_span(span);
+            lexer.output.push(RootTags::H3(H3(text, span)));
         } else {
             lexer.skip_whitespace();
             let text = lexer.read_to_eol();
-            lexer.output.push(RootTags::H2(H2(text)));
+            let span = lexer.close_span(span);
+            lexer.output.push(RootTags::H2(H2(text, span)));
         }
     } else {
         lexer.skip_whitespace();
         let text = lexer.read_to_eol();
-        lexer.output.push(RootTags::H1(H1(text)));
+        let span = lexer.close_span(span);
+        lexer.output.push(RootTags::H1(H1(text, span)));
     }
 }
 
 fn tokenize_list(lexer: &mut Lexer) {
+    let span = lexer.start_span();
     lexer.next_char();
     lexer.skip_whitespace();
+    let text_span = lexer.start_span();
     let text = lexer.read_to_eol();
-    let contents = tokenize_contents(&text);
+    let contents = tokenize_contents(&text, text_span);
     let next_li = Li {
         list_type: ListTypes::Ul,
         indent: lexer.indent,
         contents,
+        span: lexer.close_span(span),
     };
     if let Some(RootTags::Li(lists)) = lexer.output.last_mut() {
         lists.push(next_li);
@@ -205,16 +305,28 @@ fn tokenize_list(lexer: &mut Lexer) {
 }
 
 fn tokenize_ordered_list(lexer: &mut Lexer) {
-    if lexer.peek_char() == Some('.') {
-        lexer.next_char();
-        lexer.next_char();
+    let span = lexer.start_span();
+    let digits = lexer.input[lexer.position..]
+        .iter()
+        .take_while(|c| c.is_ascii_digit())
+        .count();
+    let marker = lexer.position + digits;
+    // `3.14`のような段落と区別するため、`.`の後ろは空白か行末に限る
+    if lexer.input.get(marker) == Some(&'.')
+        && matches!(lexer.input.get(marker + 1), None | Some(' ' | '\t' | '\n'))
+    {
+        for _ in 0..=digits {
+            lexer.next_char();
+        }
         lexer.skip_whitespace();
+        let text_span = lexer.start_span();
         let text = lexer.read_to_eol();
-        let contents = tokenize_contents(&text);
+        let contents = tokenize_contents(&text, text_span);
         let next_li = Li {
             list_type: ListTypes::Ol,
             indent: lexer.indent,
             contents,
+            span: lexer.close_span(span),
         };
         if let Some(RootTags::Li(lists)) = lexer.output.last_mut() {
             lists.push(next_li);
@@ -223,67 +335,88 @@ fn tokenize_ordered_list(lexer: &mut Lexer) {
         }
     } else {
         let text = lexer.read_to_eol();
-        lexer
-            .output
-            .push(RootTags::P(P(vec![Contents::Text(Text(text))])))
+        let contents = tokenize_contents(&text, span);
+        let span = lexer.close_span(span);
+        lexer.output.push(RootTags::P(P(contents, span)))
     }
 }
 
-fn tokenize_codeblock(lexer: &mut Lexer) {
+/// 閉じる```がなければファイルの最後までをコードにする
+fn tokenize_codeblock(lexer: &mut Lexer, span: Span) {
     let mut code = String::new();
     lexer.next_char();
-    loop {
+    while lexer.position < lexer.input.len() {
         let row = lexer.read_to_eol();
+        let end = lexer.close_span(span);
         lexer.next_char();
         if row.ends_with("```") {
-            break;
+            lexer.output.push(RootTags::Pre(Pre(code, end)));
+            return;
         }
         code.push_str(&(row + "\n"));
     }
-    lexer.output.push(RootTags::Pre(Pre(code)));
+    let end = lexer.close_span(span);
+    lexer.output.push(RootTags::Pre(Pre(code, end)));
 }
 
-fn tokenize_contents(text: &str) -> Vec<Contents> {
+/// `start`は`text`の先頭文字の位置
+pub(crate) fn tokenize_contents(text: &str, start: Span) -> Vec<Contents> {
     let mut contents = Vec::<Contents>::new();
-    let mut text = text.chars().peekable();
-    while let Some(c) = text.next() {
+    let span_at = |i: usize| Span {
+        start: start.start + i,
+        end: start.start + i,
+        line: start.line,
+        column: start.column + text[..i].chars().count(),
+    };
+    let close = |span: Span, text_iter: &mut std::iter::Peekable<std::str::CharIndices>| Span {
+        end: start.start + text_iter.peek().map_or(text.len(), |&(i, _)| i),
+        ..span
+    };
+    let mut text_iter = text.char_indices().peekable();
+    while let Some((i, c)) = text_iter.next() {
         match c {
             '`' => {
-                let mut code = Code(String::new());
-                while let Some(c) = text.next() {
+                let mut code = String::new();
+                for (_, c) in text_iter.by_ref() {
                     if c == '`' {
                         break;
                     }
-                    code.0.push(c);
+                    code.push(c);
                 }
-                contents.push(Contents::Code(code));
+                let span = close(span_at(i), &mut text_iter);
+                contents.push(Contents::Code(Code(code, span)));
             }
             '*' => {
-                if text.next_if_eq(&'*').is_some() {
-                    let mut bold = Bold(String::new());
-                    while let Some(c) = text.next() {
-                        if c == '*' && text.next_if_eq(&'*').is_some() {
+                if text_iter.next_if(|&(_, c)| c == '*').is_some() {
+                    let mut bold = String::new();
+                    while let Some((_, c)) = text_iter.next() {
+                        if c == '*' && text_iter.next_if(|&(_, c)| c == '*').is_some() {
                             break;
                         }
-                        bold.0.push(c);
+                        bold.push(c);
                     }
-                    contents.push(Contents::Bold(bold));
+                    let span = close(span_at(i), &mut text_iter);
+                    contents.push(Contents::Bold(Bold(bold, span)));
                 } else {
-                    let mut italic = Italic(String::new());
-                    while let Some(c) = text.next() {
+                    let mut italic = String::new();
+                    for (_, c) in text_iter.by_ref() {
                         if c == '*' {
                             break;
                         }
-                        italic.0.push(c);
+                        italic.push(c);
                     }
-                    contents.push(Contents::Italic(italic));
+                    let span = close(span_at(i), &mut text_iter);
+                    contents.push(Contents::Italic(Italic(italic, span)));
                 }
             }
             _ => {
+                let end = start.start + i + c.len_utf8();
                 if let Some(Contents::Text(text)) = contents.last_mut() {
                     text.0.push(c);
+                    text.1.end = end;
                 } else {
-                    contents.push(Contents::Text(Text(c.to_string())));
+                    let span = Span { end, ..span_at(i) };
+                    contents.push(Contents::Text(Text(c.to_string(), span)));
                 }
             }
         }
@@ -293,51 +426,115 @@ fn tokenize_contents(text: &str) -> Vec<Contents> {
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::tokenize;
+    use crate::lexer::{tokenize, tokenize_from};
 
-    use super::{Bold, Code, Contents, Italic, Li, ListTypes, Pre, RootTags, Text, H1, H2, H3, P};
+    use super::{
+        Bold, Code, Contents, Italic, Li, ListTypes, Pre, RootTags, Span, Text, H1, H2, H3, P,
+    };
+
+    fn without_spans(tags: Vec<RootTags>) -> Vec<RootTags> {
+        let strip = |contents: Vec<Contents>| {
+            contents
+                .into_iter()
+                .map(|content| match content {
+                    Contents::Text(Text(text, _)) => Contents::Text(Text(text, Span::default())),
+                    Contents::Bold(Bold(bold, _)) => Contents::Bold(Bold(bold, Span::default())),
+                    Contents::Italic(Italic(italic, _)) => {
+                        Contents::Italic(Italic(italic, Span::default()))
+                    }
+                    Contents::Code(Code(code, _)) => Contents::Code(Code(code, Span::default())),
+                })
+                .collect::<Vec<_>>()
+        };
+        tags.into_iter()
+            .map(|tag| match tag {
+                RootTags::H1(H1(text, _)) => RootTags::H1(H1(text, Span::default())),
+                RootTags::H2(H2(text, _)) => RootTags::H2(H2(text, Span::default())),
+                RootTags::H3(H3(text, _)) => RootTags::H3(H3(text, Span::default())),
+                RootTags::P(P(contents, _)) => RootTags::P(P(strip(contents), Span::default())),
+                RootTags::Li(lists) => RootTags::Li(
+                    lists
+                        .into_iter()
+                        .map(|li| Li {
+                            contents: strip(li.contents),
+                            span: Span::default(),
+                            ..li
+                        })
+                        .collect(),
+                ),
+                RootTags::Pre(Pre(code, _)) => RootTags::Pre(Pre(code, Span::default())),
+                tag => tag,
+            })
+            .collect()
+    }
 
     #[test]
     fn test_tokenize() {
         let tests = [
             (
                 "テキスト`コード`",
-                vec![RootTags::P(P(vec![
-                    Contents::Text(Text("テキスト".to_string())),
-                    Contents::Code(Code("コード".to_string())),
-                ]))],
+                vec![RootTags::P(P(
+                    vec![
+                        Contents::Text(Text("テキスト".to_string(), Span::default())),
+                        Contents::Code(Code("コード".to_string(), Span::default())),
+                    ],
+                    Span::default(),
+                ))],
             ),
             (
                 "*イタリック*",
-                vec![RootTags::P(P(vec![Contents::Italic(Italic(
-                    "イタリック".to_string(),
-                ))]))],
+                vec![RootTags::P(P(
+                    vec![Contents::Italic(Italic(
+                        "イタリック".to_string(),
+                        Span::default(),
+                    ))],
+                    Span::default(),
+                ))],
             ),
             (
                 "**ボールド**",
-                vec![RootTags::P(P(vec![Contents::Bold(Bold(
-                    "ボールド".to_string(),
-                ))]))],
+                vec![RootTags::P(P(
+                    vec![Contents::Bold(Bold(
+                        "ボールド".to_string(),
+                        Span::default(),
+                    ))],
+                    Span::default(),
+                ))],
             ),
             (
                 "*イタリック***ボールド**",
-                vec![RootTags::P(P(vec![
-                    Contents::Italic(Italic("イタリック".to_string())),
-                    Contents::Bold(Bold("ボールド".to_string())),
-                ]))],
+                vec![RootTags::P(P(
+                    vec![
+                        Contents::Italic(Italic("イタリック".to_string(), Span::default())),
+                        Contents::Bold(Bold("ボールド".to_string(), Span::default())),
+                    ],
+                    Span::default(),
+                ))],
             ),
             (
                 "テキスト*イタリック*`コード`**ボールド**",
-                vec![RootTags::P(P(vec![
-                    Contents::Text(Text("テキスト".to_string())),
-                    Contents::Italic(Italic("イタリック".to_string())),
-                    Contents::Code(Code("コード".to_string())),
-                    Contents::Bold(Bold("ボールド".to_string())),
-                ]))],
+                vec![RootTags::P(P(
+                    vec![
+                        Contents::Text(Text("テキスト".to_string(), Span::default())),
+                        Contents::Italic(Italic("イタリック".to_string(), Span::default())),
+                        Contents::Code(Code("コード".to_string(), Span::default())),
+                        Contents::Bold(Bold("ボールド".to_string(), Span::default())),
+                    ],
+                    Span::default(),
+                ))],
+            ),
+            (
+                "# 見出し1",
+                vec![RootTags::H1(H1("見出し1".to_string(), Span::default()))],
+            ),
+            (
+                "## 見出し2",
+                vec![RootTags::H2(H2("見出し2".to_string(), Span::default()))],
+            ),
+            (
+                "### 見出し3",
+                vec![RootTags::H3(H3("見出し3".to_string(), Span::default()))],
             ),
-            ("# 見出し1", vec![RootTags::H1(H1("見出し1".to_string()))]),
-            ("## 見出し2", vec![RootTags::H2(H2("見出し2".to_string()))]),
-            ("### 見出し3", vec![RootTags::H3(H3("見出し3".to_string()))]),
             (
                 "# 見出し1
 
@@ -345,16 +542,17 @@ mod tests {
 
 ### 見出し3",
                 vec![
-                    RootTags::H1(H1("見出し1".to_string())),
-                    RootTags::H2(H2("見出し2".to_string())),
-                    RootTags::H3(H3("見出し3".to_string())),
+                    RootTags::H1(H1("見出し1".to_string(), Span::default())),
+                    RootTags::H2(H2("見出し2".to_string(), Span::default())),
+                    RootTags::H3(H3("見出し3".to_string(), Span::default())),
                 ],
             ),
             (
                 "段落\n",
-                vec![RootTags::P(P(vec![Contents::Text(Text(
-                    "段落".to_string(),
-                ))]))],
+                vec![RootTags::P(P(
+                    vec![Contents::Text(Text("段落".to_string(), Span::default()))],
+                    Span::default(),
+                ))],
             ),
             (
                 "# 見出し1
@@ -365,10 +563,13 @@ mod tests {
 
 段落",
                 vec![
-                    RootTags::H1(H1("見出し1".to_string())),
-                    RootTags::H2(H2("見出し2".to_string())),
-                    RootTags::H3(H3("見出し3".to_string())),
-                    RootTags::P(P(vec![Contents::Text(Text("段落".to_string()))])),
+                    RootTags::H1(H1("見出し1".to_string(), Span::default())),
+                    RootTags::H2(H2("見出し2".to_string(), Span::default())),
+                    RootTags::H3(H3("見出し3".to_string(), Span::default())),
+                    RootTags::P(P(
+                        vec![Contents::Text(Text("段落".to_string(), Span::default()))],
+                        Span::default(),
+                    )),
                 ],
             ),
             (
@@ -376,7 +577,8 @@ mod tests {
                 vec![RootTags::Li(vec![Li {
                     list_type: ListTypes::Ul,
                     indent: 0,
-                    contents: vec![Contents::Text(Text("リスト".to_string()))],
+                    contents: vec![Contents::Text(Text("リスト".to_string(), Span::default()))],
+                    span: Span::default(),
                 }])],
             ),
             (
@@ -386,12 +588,20 @@ mod tests {
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                 ])],
             ),
@@ -405,22 +615,38 @@ mod tests {
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 1,
-                        contents: vec![Contents::Text(Text("リスト2-1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 1,
-                        contents: vec![Contents::Text(Text("リスト2-2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                 ])],
             ),
@@ -436,32 +662,56 @@ mod tests {
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 2,
-                        contents: vec![Contents::Text(Text("リスト2-1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 4,
-                        contents: vec![Contents::Text(Text("リスト2-1-1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-1-1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 2,
-                        contents: vec![Contents::Text(Text("リスト2-2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト3".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト3".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                 ])],
             ),
@@ -471,11 +721,12 @@ mod tests {
                     list_type: ListTypes::Ul,
                     indent: 0,
                     contents: vec![
-                        Contents::Text(Text("テキスト".to_string())),
-                        Contents::Italic(Italic("イタリック".to_string())),
-                        Contents::Code(Code("コード".to_string())),
-                        Contents::Bold(Bold("ボールド".to_string())),
+                        Contents::Text(Text("テキスト".to_string(), Span::default())),
+                        Contents::Italic(Italic("イタリック".to_string(), Span::default())),
+                        Contents::Code(Code("コード".to_string(), Span::default())),
+                        Contents::Bold(Bold("ボールド".to_string(), Span::default())),
                     ],
+                    span: Span::default(),
                 }])],
             ),
             (
@@ -486,21 +737,23 @@ mod tests {
                         list_type: ListTypes::Ul,
                         indent: 0,
                         contents: vec![
-                            Contents::Text(Text("テキスト".to_string())),
-                            Contents::Italic(Italic("イタリック".to_string())),
-                            Contents::Code(Code("コード".to_string())),
-                            Contents::Bold(Bold("ボールド".to_string())),
+                            Contents::Text(Text("テキスト".to_string(), Span::default())),
+                            Contents::Italic(Italic("イタリック".to_string(), Span::default())),
+                            Contents::Code(Code("コード".to_string(), Span::default())),
+                            Contents::Bold(Bold("ボールド".to_string(), Span::default())),
                         ],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ul,
                         indent: 2,
                         contents: vec![
-                            Contents::Text(Text("テキスト".to_string())),
-                            Contents::Italic(Italic("イタリック".to_string())),
-                            Contents::Code(Code("コード".to_string())),
-                            Contents::Bold(Bold("ボールド".to_string())),
+                            Contents::Text(Text("テキスト".to_string(), Span::default())),
+                            Contents::Italic(Italic("イタリック".to_string(), Span::default())),
+                            Contents::Code(Code("コード".to_string(), Span::default())),
+                            Contents::Bold(Bold("ボールド".to_string(), Span::default())),
                         ],
+                        span: Span::default(),
                     },
                 ])],
             ),
@@ -510,11 +763,12 @@ mod tests {
                     list_type: ListTypes::Ol,
                     indent: 0,
                     contents: vec![
-                        Contents::Text(Text("テキスト".to_string())),
-                        Contents::Italic(Italic("イタリック".to_string())),
-                        Contents::Code(Code("コード".to_string())),
-                        Contents::Bold(Bold("ボールド".to_string())),
+                        Contents::Text(Text("テキスト".to_string(), Span::default())),
+                        Contents::Italic(Italic("イタリック".to_string(), Span::default())),
+                        Contents::Code(Code("コード".to_string(), Span::default())),
+                        Contents::Bold(Bold("ボールド".to_string(), Span::default())),
                     ],
+                    span: Span::default(),
                 }])],
             ),
             (
@@ -523,7 +777,8 @@ console.log('Hello, world!');
 ```
 ",
                 vec![RootTags::Pre(Pre(
-                    "console.log('Hello, world!');\n".to_string()
+                    "console.log('Hello, world!');\n".to_string(),
+                    Span::default(),
                 ))],
             ),
             (
@@ -535,6 +790,7 @@ add(a, b);
 ",
                 vec![RootTags::Pre(Pre(
                     "const a = 1;\nconst b = 2;\nadd(a, b);\n".to_string(),
+                    Span::default(),
                 ))],
             ),
             (
@@ -551,28 +807,49 @@ add(a, b);
 段落3
 ",
                 vec![
-                    RootTags::H1(H1("見出し1".to_string())),
-                    RootTags::H2(H2("見出し2".to_string())),
-                    RootTags::P(P(vec![Contents::Text(Text("段落1".to_string()))])),
-                    RootTags::P(P(vec![Contents::Text(Text("段落2".to_string()))])),
+                    RootTags::H1(H1("見出し1".to_string(), Span::default())),
+                    RootTags::H2(H2("見出し2".to_string(), Span::default())),
+                    RootTags::P(P(
+                        vec![Contents::Text(Text("段落1".to_string(), Span::default()))],
+                        Span::default(),
+                    )),
+                    RootTags::P(P(
+                        vec![Contents::Text(Text("段落2".to_string(), Span::default()))],
+                        Span::default(),
+                    )),
                     RootTags::Li(vec![
                         Li {
                             list_type: ListTypes::Ul,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト1".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                         Li {
                             list_type: ListTypes::Ul,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト2".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                         Li {
                             list_type: ListTypes::Ul,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト3".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト3".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                     ]),
-                    RootTags::P(P(vec![Contents::Text(Text("段落3".to_string()))])),
+                    RootTags::P(P(
+                        vec![Contents::Text(Text("段落3".to_string(), Span::default()))],
+                        Span::default(),
+                    )),
                 ],
             ),
             (
@@ -580,14 +857,38 @@ add(a, b);
                 vec![RootTags::Li(vec![Li {
                     list_type: ListTypes::Ol,
                     indent: 0,
-                    contents: vec![Contents::Text(Text("リスト".to_string()))],
+                    contents: vec![Contents::Text(Text("リスト".to_string(), Span::default()))],
+                    span: Span::default(),
                 }])],
             ),
+            (
+                "2. リスト",
+                vec![RootTags::Li(vec![Li {
+                    list_type: ListTypes::Ol,
+                    indent: 0,
+                    contents: vec![Contents::Text(Text("リスト".to_string(), Span::default()))],
+                    span: Span::default(),
+                }])],
+            ),
+            (
+                "3.14は**円周率**",
+                vec![RootTags::P(P(
+                    vec![
+                        Contents::Text(Text("3.14は".to_string(), Span::default())),
+                        Contents::Bold(Bold("円周率".to_string(), Span::default())),
+                    ],
+                    Span::default(),
+                ))],
+            ),
             (
                 "1リストではない段落",
-                vec![RootTags::P(P(vec![Contents::Text(Text(
-                    "1リストではない段落".to_string(),
-                ))]))],
+                vec![RootTags::P(P(
+                    vec![Contents::Text(Text(
+                        "1リストではない段落".to_string(),
+                        Span::default(),
+                    ))],
+                    Span::default(),
+                ))],
             ),
             (
                 "1. リスト1
@@ -596,12 +897,20 @@ add(a, b);
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                 ])],
             ),
@@ -615,22 +924,38 @@ add(a, b);
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 1,
-                        contents: vec![Contents::Text(Text("リスト2-1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 1,
-                        contents: vec![Contents::Text(Text("リスト2-2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                 ])],
             ),
@@ -646,32 +971,56 @@ add(a, b);
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 2,
-                        contents: vec![Contents::Text(Text("リスト2-1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 4,
-                        contents: vec![Contents::Text(Text("リスト2-1-1".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-1-1".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 2,
-                        contents: vec![Contents::Text(Text("リスト2-2".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト2-2".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                     Li {
                         list_type: ListTypes::Ol,
                         indent: 0,
-                        contents: vec![Contents::Text(Text("リスト3".to_string()))],
+                        contents: vec![Contents::Text(Text(
+                            "リスト3".to_string(),
+                            Span::default(),
+                        ))],
+                        span: Span::default(),
                     },
                 ])],
             ),
@@ -705,52 +1054,206 @@ console.log('Hello, world!');
 段落3
 ",
                 vec![
-                    RootTags::H1(H1("見出し1".to_string())),
-                    RootTags::H2(H2("見出し2".to_string())),
-                    RootTags::P(P(vec![Contents::Text(Text("段落1".to_string()))])),
-                    RootTags::Pre(Pre("console.log('Hello, world!');\n".to_string())),
-                    RootTags::P(P(vec![Contents::Text(Text("段落2".to_string()))])),
-                    RootTags::Pre(Pre("console.log('Hello, world!');\n".to_string())),
+                    RootTags::H1(H1("見出し1".to_string(), Span::default())),
+                    RootTags::H2(H2("見出し2".to_string(), Span::default())),
+                    RootTags::P(P(
+                        vec![Contents::Text(Text("段落1".to_string(), Span::default()))],
+                        Span::default(),
+                    )),
+                    RootTags::Pre(Pre(
+                        "console.log('Hello, world!');\n".to_string(),
+                        Span::default(),
+                    )),
+                    RootTags::P(P(
+                        vec![Contents::Text(Text("段落2".to_string(), Span::default()))],
+                        Span::default(),
+                    )),
+                    RootTags::Pre(Pre(
+                        "console.log('Hello, world!');\n".to_string(),
+                        Span::default(),
+                    )),
                     RootTags::Li(vec![
                         Li {
                             list_type: ListTypes::Ol,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト1".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                         Li {
                             list_type: ListTypes::Ol,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト2".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                         Li {
                             list_type: ListTypes::Ol,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト3".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト3".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                         Li {
                             list_type: ListTypes::Ul,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト1".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト1".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                         Li {
                             list_type: ListTypes::Ul,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト2".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト2".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                         Li {
                             list_type: ListTypes::Ul,
                             indent: 0,
-                            contents: vec![Contents::Text(Text("リスト3".to_string()))],
+                            contents: vec![Contents::Text(Text(
+                                "リスト3".to_string(),
+                                Span::default(),
+                            ))],
+                            span: Span::default(),
                         },
                     ]),
-                    RootTags::Pre(Pre("console.log('Hello, world!');\n".to_string())),
-                    RootTags::P(P(vec![Contents::Text(Text("段落3".to_string()))])),
+                    RootTags::Pre(Pre(
+                        "console.log('Hello, world!');\n".to_string(),
+                        Span::default(),
+                    )),
+                    RootTags::P(P(
+                        vec![Contents::Text(Text("段落3".to_string(), Span::default()))],
+                        Span::default(),
+                    )),
                 ],
             ),
         ];
 
         for (input, output) in tests {
-            assert_eq!(tokenize(input), output);
+            assert_eq!(without_spans(tokenize(input)), output);
         }
     }
+
+    #[test]
+    fn test_tokenize_span() {
+        let tags =
+            tokenize("# 見出し1\n\n段落`コード`\n\n- リスト1\n\t- **リスト2**\n```\ncode\n```\n");
+        assert_eq!(
+            tags.iter().map(RootTags::span).collect::<Vec<_>>(),
+            vec![
+                Span {
+                    start: 0,
+                    end: 12,
+                    line: 1,
+                    column: 1
+                },
+                Span {
+                    start: 14,
+                    end: 31,
+                    line: 3,
+                    column: 1
+                },
+                Span {
+                    start: 33,
+                    end: 63,
+                    line: 5,
+                    column: 1
+                },
+                Span {
+                    start: 64,
+                    end: 76,
+                    line: 7,
+                    column: 1
+                },
+            ]
+        );
+
+        let RootTags::P(P(contents, _)) = &tags[1] else {
+            panic!("expected paragraph: {:?}", tags[1]);
+        };
+        assert_eq!(
+            contents[1].span(),
+            Span {
+                start: 20,
+                end: 31,
+                line: 3,
+                column: 3
+            }
+        );
+
+        let RootTags::Li(lists) = &tags[2] else {
+            panic!("expected list: {:?}", tags[2]);
+        };
+        assert_eq!(
+            lists[1].span,
+            Span {
+                start: 47,
+                end: 63,
+                line: 6,
+                column: 2
+            }
+        );
+        assert_eq!(
+            lists[1].contents[0].span(),
+            Span {
+                start: 49,
+                end: 63,
+                line: 6,
+                column: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unclosed_codeblock() {
+        let tags = tokenize("段落\n```\ncode\n\nmore");
+        assert_eq!(
+            tags[1].span(),
+            Span {
+                start: 7,
+                end: 21,
+                line: 2,
+                column: 1
+            }
+        );
+        assert_eq!(
+            without_spans(tags),
+            vec![
+                RootTags::P(P(
+                    vec![Contents::Text(Text("段落".to_string(), Span::default()))],
+                    Span::default(),
+                )),
+                RootTags::Pre(Pre("code\n\nmore\n".to_string(), Span::default())),
+            ]
+        );
+        assert_eq!(
+            without_spans(tokenize("```")),
+            vec![RootTags::Pre(Pre(String::new(), Span::default()))]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_from() {
+        let tags = tokenize_from("# 見出し1\n", 30, 4);
+        assert_eq!(
+            tags[0].span(),
+            Span {
+                start: 30,
+                end: 42,
+                line: 4,
+                column: 1
+            }
+        );
+    }
 }
@@ -0,0 +1,288 @@
+pub mod build;
+pub mod config;
+pub mod data;
+pub mod error;
+pub mod feed;
+pub mod formatter;
+pub mod front_matter;
+pub mod index;
+pub mod lexer;
+pub mod manifest;
+pub mod parser;
+pub mod plain_text;
+pub mod publish;
+pub mod serve;
+pub mod sitemap;
+pub mod tags;
+pub mod template;
+pub mod view;
+pub mod watch;
+
+pub use crate::error::{Error, Result};
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    front_matter::{parse_params, Params},
+    lexer::{tokenize_from, RootTags},
+    parser::parse,
+    plain_text::{char_count, excerpt, heading_text, reading_time, to_plain_text},
+    template::{Context, ToValue},
+};
+
+pub struct Options {
+    /// 先頭のfront matterを読むかどうか
+    pub front_matter: bool,
+    /// `excerpt`の最大文字数(書記素単位)
+    pub excerpt_length: usize,
+    /// タイトルを最初の見出しから取った場合に、その見出しを本文から取り除くかどうか
+    /// (テンプレート側でタイトルを表示する場合用)
+    pub drop_title_heading: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            front_matter: true,
+            excerpt_length: 120,
+            drop_title_heading: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Rendered {
+    /// front matterの`title`、なければ最初の見出しのテキスト
+    pub title: String,
+    /// front matterに`description`がなければ`excerpt`と同じ
+    pub description: String,
+    pub excerpt: String,
+    /// 読了時間(分)
+    pub reading_time: usize,
+    pub char_count: usize,
+    pub content: String,
+    /// front matterの`title`と`description`以外の値
+    pub front_matter: BTreeMap<String, String>,
+}
+
+impl Rendered {
+    /// front matterの値と、計算した`title`・`content`などをテンプレートの変数にする。
+    /// 同じ名前の場合は計算した値を優先する
+    pub fn to_context(&self) -> Context {
+        let mut context = self
+            .front_matter
+            .iter()
+            .map(|(key, value)| (key.clone(), value.to_value()))
+            .collect::<Context>();
+        for (name, value) in [
+            ("title", self.title.clone()),
+            ("description", self.description.clone()),
+            ("excerpt", self.excerpt.clone()),
+            ("reading_time", self.reading_time.to_string()),
+            ("char_count", self.char_count.to_string()),
+            ("content", self.content.clone()),
+        ] {
+            context.insert(name.to_string(), value.to_value());
+        }
+        context
+    }
+}
+
+/// front matterとトークン列。JSONにしてフロントエンド側で描画するためのもの
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub front_matter: Params,
+    pub tags: Vec<RootTags>,
+}
+
+pub fn markdown_to_document(markdown: &str, options: &Options) -> Result<Document> {
+    let front_matter = if options.front_matter {
+        parse_params(markdown)?
+    } else {
+        Params::default()
+    };
+
+    let tags = tokenize_from(
+        markdown.get(front_matter.skip_cnt..).unwrap_or_default(),
+        front_matter.skip_cnt,
+        front_matter.skip_lines + 1,
+    );
+
+    Ok(Document { front_matter, tags })
+}
+
+pub fn markdown_to_html(markdown: &str, options: &Options) -> Result<Rendered> {
+    document_to_html(markdown_to_document(markdown, options)?, options)
+}
+
+pub fn document_to_html(mut document: Document, options: &Options) -> Result<Rendered> {
+    let title = if document.front_matter.title.is_empty() {
+        let (i, text) = document
+            .tags
+            .iter()
+            .enumerate()
+            .find_map(|(i, tag)| match tag {
+                RootTags::H1(h1) => Some((i, &h1.0)),
+                RootTags::H2(h2) => Some((i, &h2.0)),
+                RootTags::H3(h3) => Some((i, &h3.0)),
+                _ => None,
+            })
+            .ok_or(Error::TitleNotFound)?;
+        let title = heading_text(text);
+        if options.drop_title_heading {
+            document.tags.remove(i);
+        }
+        title
+    } else {
+        std::mem::take(&mut document.front_matter.title)
+    };
+
+    let excerpt = excerpt(&document.tags, options.excerpt_length);
+    let description = if document.front_matter.description.is_empty() {
+        excerpt.clone()
+    } else {
+        document.front_matter.description
+    };
+    let text = to_plain_text(&document.tags, false);
+    let content = parse(document.tags);
+
+    Ok(Rendered {
+        title,
+        description,
+        excerpt,
+        reading_time: reading_time(&text),
+        char_count: char_count(&text),
+        content,
+        front_matter: document.front_matter.fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        document_to_html, markdown_to_document, markdown_to_html, template::ToValue, Document,
+        Error, Options, Rendered,
+    };
+
+    #[test]
+    fn test_markdown_to_html() {
+        let markdown = "---\ndescription: 説明\n---\n# 見出し1\n段落\n";
+        assert_eq!(
+            markdown_to_html(markdown, &Options::default()).unwrap(),
+            Rendered {
+                title: "見出し1".to_string(),
+                description: "説明".to_string(),
+                excerpt: "段落".to_string(),
+                reading_time: 1,
+                char_count: 6,
+                content: "<h1>見出し1</h1>\n<p>段落</p>\n".to_string(),
+                front_matter: BTreeMap::new(),
+            }
+        );
+
+        let options = Options {
+            front_matter: false,
+            ..Options::default()
+        };
+        assert_eq!(
+            markdown_to_html("# 見出し1\n", &options).unwrap(),
+            Rendered {
+                title: "見出し1".to_string(),
+                description: String::new(),
+                excerpt: String::new(),
+                reading_time: 1,
+                char_count: 4,
+                content: "<h1>見出し1</h1>\n".to_string(),
+                front_matter: BTreeMap::new(),
+            }
+        );
+
+        let options = Options {
+            excerpt_length: 5,
+            ..Options::default()
+        };
+        let rendered =
+            markdown_to_html("---\n---\n# 見出し1\n長い**段落**です\n", &options).unwrap();
+        assert_eq!(rendered.description, "長い段落で…");
+        assert_eq!(rendered.excerpt, "長い段落で…");
+
+        // front matterがなくても本文を読む
+        let rendered = markdown_to_html("# 見出し1\n段落\n", &Options::default()).unwrap();
+        assert_eq!(rendered.title, "見出し1");
+        assert_eq!(rendered.content, "<h1>見出し1</h1>\n<p>段落</p>\n");
+        assert!(matches!(
+            markdown_to_html("---\ntitle: タイトル\n段落\n", &Options::default()),
+            Err(Error::FrontMatterNotClosed)
+        ));
+    }
+
+    #[test]
+    fn test_to_context() {
+        let markdown = "---\ntitle: タイトル\nauthor: 著者\ncontent: 上書きされる\n---\n段落\n";
+        let context = markdown_to_html(markdown, &Options::default())
+            .unwrap()
+            .to_context();
+        assert_eq!(context["author"], "著者".to_value());
+        assert_eq!(context["title"], "タイトル".to_value());
+        assert_eq!(context["content"], "<p>段落</p>\n".to_value());
+        assert_eq!(context["reading_time"], "1".to_value());
+    }
+
+    #[test]
+    fn test_title() {
+        let title = |markdown: &str, options: &Options| {
+            markdown_to_html(markdown, options).map(|rendered| (rendered.title, rendered.content))
+        };
+        let options = Options::default();
+
+        assert_eq!(
+            title("---\ntitle: タイトル\n---\n# 見出し1\n", &options).unwrap(),
+            ("タイトル".to_string(), "<h1>見出し1</h1>\n".to_string())
+        );
+        assert_eq!(
+            title("---\n---\n段落\n## Rustの**所有権**\n", &options).unwrap(),
+            (
+                "Rustの所有権".to_string(),
+                "<p>段落</p>\n<h2>Rustの**所有権**</h2>\n".to_string()
+            )
+        );
+        assert!(matches!(
+            title("---\n---\n", &options),
+            Err(Error::TitleNotFound)
+        ));
+        assert!(matches!(
+            title("---\n---\n段落\n", &options),
+            Err(Error::TitleNotFound)
+        ));
+
+        let options = Options {
+            drop_title_heading: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            title("---\n---\n# 見出し1\n段落\n", &options).unwrap(),
+            ("見出し1".to_string(), "<p>段落</p>\n".to_string())
+        );
+        assert_eq!(
+            title("---\ntitle: タイトル\n---\n# 見出し1\n", &options).unwrap(),
+            ("タイトル".to_string(), "<h1>見出し1</h1>\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_document_json() {
+        let markdown = "---\ndescription: 説明\n---\n# 見出し1\n- **リスト**\n";
+        let document = markdown_to_document(markdown, &Options::default()).unwrap();
+        let json = serde_json::to_string(&document).unwrap();
+        let restored: Document = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.tags, document.tags);
+        assert_eq!(
+            document_to_html(restored, &Options::default()).unwrap(),
+            markdown_to_html(markdown, &Options::default()).unwrap()
+        );
+    }
+}
@@ -1,88 +1,251 @@
-use serde::{Deserialize, Serialize};
-use std::{collections::VecDeque, env::args};
+use std::{env::args, fs, process::exit, thread, time::Duration};
 
-use crate::{lexer::tokenize, parser::parse, view::view};
+use chrono::Local;
 
-mod lexer;
-mod parser;
-mod view;
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Data {
-    slug: String,
-    title: String,
-    created_at: String,
-}
+use blog_parser::{
+    build::{build, build_with_cache, Summary},
+    config::Config,
+    data, document_to_html,
+    error::Error,
+    formatter::format,
+    markdown_to_document, markdown_to_html,
+    publish::{insert_banner, Status},
+    serve::Server,
+    template::{cache::TemplateCache, ToValue},
+    view::view,
+    watch::Watcher,
+    Options, Rendered,
+};
 
 fn main() {
     let args = args().collect::<Vec<String>>();
-    if args.len() < 5 {
-        panic!(
-            "Usage: {} <markdown> <template> <data_json> <slug>",
-            args[0]
-        );
+    let strict = args.iter().any(|arg| arg == "--strict");
+    let preview = args.iter().any(|arg| arg == "--preview");
+    let args = args
+        .into_iter()
+        .filter(|arg| arg != "--strict" && arg != "--preview")
+        .collect::<Vec<_>>();
+    let result = match args.get(1).map(String::as_str) {
+        Some("ast") if args.len() >= 3 => ast(&args[2]),
+        Some("render-ast") if args.len() >= 3 => render_ast(&args[2]),
+        Some("fmt") if args.len() >= 3 => fmt(&args[2..]),
+        Some("build") if args.len() <= 3 => build_site(args.get(2), strict, preview),
+        Some("watch") if args.len() <= 3 => watch_site(args.get(2), strict, preview),
+        Some("serve") if args.len() <= 3 => serve_site(args.get(2), strict),
+        _ if args.len() >= 5 => run(&args[1], &args[2], &args[3], &args[4], strict, preview),
+        _ => {
+            eprintln!(
+                "Usage: {0} [--strict] [--preview] <markdown> <template> <data_json> <slug>
+       {0} ast <markdown>
+       {0} render-ast <json>
+       {0} fmt [--check] <markdown_file>...
+       {0} [--strict] [--preview] build [<config_json>]
+       {0} [--strict] [--preview] watch [<config_json>]
+       {0} [--strict] serve [<config_json>]",
+                args[0]
+            );
+            exit(2);
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        exit(1);
     }
+}
+
+/// 描画のオプションと更新日の数は、あれば`blog.json`の設定を使う。
+/// `strict`の場合はテンプレートの未定義の変数をエラーにし、使われない変数を警告する。
+/// 下書きと公開前の記事は記事の一覧に入れず、`preview`の場合だけ公開前とわかる帯を付けて出力する
+/// (`preview`でなければ何も出力せずに`Error::Unpublished`を返す)
+fn run(
+    markdown: &str,
+    template: &str,
+    data_json: &str,
+    slug: &str,
+    strict: bool,
+    preview: bool,
+) -> blog_parser::Result<()> {
+    let config = Config::load(CONFIG)?;
+    let rendered = markdown_to_html(markdown, &config.options())?;
+    let status = Status::of(&rendered, Local::now().fixed_offset())?;
 
-    let Params {
-        description,
-        skip_cnt,
-    } = parse_params(&args[1]);
-
-    let content = parse(tokenize(&args[1][skip_cnt..]));
-
-    let title = content
-        .lines()
-        .next()
-        .unwrap()
-        .replace("<h1>", "")
-        .replace("</h1>", "");
-
-    let mut data: VecDeque<Data> =
-        serde_json::from_str(std::fs::read_to_string(&args[3]).unwrap().as_str()).unwrap();
-    let date;
-    if let Some(article) = data.iter_mut().find(|data| data.slug == args[4]) {
-        article.title = title.clone();
-        date = article.created_at.clone();
+    let mut data = data::read(data_json)?;
+    let today = data::today();
+    let (date, updated_at) = if status.is_published() {
+        let date = data::upsert(&mut data, slug, &rendered, &today, config.revision_limit);
+        let updated_at = data
+            .iter()
+            .find(|data| data.slug == slug)
+            .map_or(today, |data| data.updated_at().to_string());
+        (date, updated_at)
     } else {
-        date = chrono::Local::now().format("%Y-%m-%d").to_string();
-        data.push_front(Data {
-            slug: args[4].clone(),
-            title: title.clone(),
-            created_at: date.clone(),
+        data.retain(|data| data.slug != slug);
+        (today.clone(), today)
+    };
+    data::write(data_json, &data)?;
+    if !status.is_published() && !preview {
+        return Err(Error::Unpublished {
+            slug: slug.to_string(),
         });
     }
-    std::fs::write(&args[3], serde_json::to_string_pretty(&data).unwrap()).unwrap();
 
-    let mut html = view(&args[2]);
-    compact!(html, title, content, date, description);
-    print!("{}", html);
+    let mut variables = rendered.to_context();
+    variables.insert("date".to_string(), date.to_value());
+    variables.insert("updated_at".to_string(), updated_at.to_value());
+    let template = view(template)?;
+    let html = if strict {
+        let (html, unused) = template.render_strict(&variables)?;
+        for name in unused {
+            eprintln!("warning: unused template variable `{}`", name);
+        }
+        html
+    } else {
+        template.render(&variables)
+    };
+    print!("{}", insert_banner(&html, &status));
+    Ok(())
 }
 
-struct Params<'a> {
-    description: &'a str,
-    skip_cnt: usize,
+fn ast(markdown: &str) -> blog_parser::Result<()> {
+    let document = markdown_to_document(markdown, &Options::default())?;
+    println!("{}", serde_json::to_string_pretty(&document)?);
+    Ok(())
 }
 
-fn parse_params(markdown: &str) -> Params<'_> {
-    let mut description = "";
-    let mut skip_cnt = 0;
-    let mut cnt = 0;
-    for line in markdown.lines() {
-        skip_cnt += line.len() + 1;
-        if line.starts_with("---") {
-            cnt += 1;
-            if cnt == 2 {
-                break;
+fn render_ast(json: &str) -> blog_parser::Result<()> {
+    let Rendered { content, .. } =
+        document_to_html(serde_json::from_str(json)?, &Options::default())?;
+    print!("{}", content);
+    Ok(())
+}
+
+/// `--check`の場合はファイルを書き換えず、整形が必要なファイルがあれば終了コード1で終わる
+fn fmt(args: &[String]) -> blog_parser::Result<()> {
+    let check = args.iter().any(|arg| arg == "--check");
+    let mut failed = false;
+    for path in args.iter().filter(|arg| *arg != "--check") {
+        let markdown = fs::read_to_string(path)?;
+        let formatted = match format(&markdown, &Options::default()) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                eprintln!("error: {}: {}", path, err);
+                failed = true;
+                continue;
             }
-        } else if line.starts_with("description:") {
-            description = line.trim_start_matches("description:").trim();
+        };
+        if formatted == markdown {
+            continue;
         }
+        if check {
+            println!("{} is not formatted", path);
+            failed = true;
+        } else {
+            fs::write(path, formatted)?;
+        }
+    }
+    if failed {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// 設定のJSONの既定のパス
+const CONFIG: &str = "blog.json";
+
+fn build_site(config: Option<&String>, strict: bool, preview: bool) -> blog_parser::Result<()> {
+    let config = Config::load(config.map_or(CONFIG, String::as_str))?;
+    let summary = build(&config, strict, preview)?;
+    print_summary(&summary);
+    if !summary.errors.is_empty() {
+        exit(1);
     }
-    if description.is_empty() {
-        panic!("description not found in markdown");
+    Ok(())
+}
+
+/// ファイルの変更を確認する間隔
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+fn watch_site(config: Option<&String>, strict: bool, preview: bool) -> blog_parser::Result<()> {
+    let config = Config::load(config.map_or(CONFIG, String::as_str))?;
+    watch(&config, strict, preview, |_| {})
+}
+
+/// ビルドした出力を`localhost`で配信し、記事やテンプレートの変更でビルドしたらブラウザを読み直させる。
+/// 下書きと公開前の記事も確認できるように、常にプレビューとしてビルドする
+fn serve_site(config: Option<&String>, strict: bool) -> blog_parser::Result<()> {
+    let config = Config::load(config.map_or(CONFIG, String::as_str))?;
+    let server = Server::bind(("127.0.0.1", config.port), &config.output_dir)?;
+    println!("serving on http://{}/", server.local_addr()?);
+    let reloader = server.reloader();
+    thread::spawn(move || {
+        if let Err(err) = server.run() {
+            eprintln!("error: {}", err);
+            exit(1);
+        }
+    });
+    // 一覧やタグのテンプレートの変更と記事の削除では記事を書き出さないので、ビルドのたびに読み直させる
+    watch(&config, strict, true, |_| reloader.reload())
+}
+
+/// 記事とテンプレートのディレクトリを監視し、変更があれば差分ビルドして`on_build`を呼ぶ。
+/// ビルドが書き出すファイルは監視しない。ビルドのエラーは表示して監視を続ける
+fn watch(
+    config: &Config,
+    strict: bool,
+    preview: bool,
+    mut on_build: impl FnMut(&Summary),
+) -> blog_parser::Result<()> {
+    let mut watcher = Watcher::new(
+        config.watch_dirs(),
+        vec![
+            config.output_dir.clone(),
+            config.data.clone(),
+            config.manifest.clone(),
+        ],
+    );
+    // 変わっていないテンプレートはビルドのたびに読み直さない
+    let mut cache = TemplateCache::new();
+    let mut changed = true;
+    loop {
+        if changed {
+            match build_with_cache(config, &mut cache, strict, preview) {
+                Ok(summary) => {
+                    print_summary(&summary);
+                    on_build(&summary);
+                }
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+        thread::sleep(WATCH_INTERVAL);
+
+        let changes = watcher.changes();
+        for path in &changes {
+            println!("changed: {}", path.display());
+        }
+        changed = !changes.is_empty();
+    }
+}
+
+fn print_summary(summary: &Summary) {
+    for (path, name) in &summary.warnings {
+        eprintln!(
+            "warning: {}: unused template variable `{}`",
+            path.display(),
+            name
+        );
     }
-    Params {
-        description,
-        skip_cnt,
+    for (path, err) in &summary.errors {
+        eprintln!("error: {}: {}", path.display(), err);
     }
+    println!(
+        "built {} pages, {} unchanged, {} unpublished, {} removed, {} errors, {} index pages, {} tag pages",
+        summary.built.len(),
+        summary.skipped.len(),
+        summary.unpublished.len(),
+        summary.removed.len(),
+        summary.errors.len(),
+        summary.index_pages,
+        summary.tag_pages
+    );
 }
@@ -0,0 +1,85 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// 前回の`build`で書き出したページごとの入力のハッシュ
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// slugとハッシュ
+    pub pages: BTreeMap<String, String>,
+    /// 書き出したタグのページ(`output_dir`からの相対パス)。次のビルドでなくなったものだけを消す
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<PathBuf>,
+}
+
+impl Manifest {
+    /// ファイルがなければ空のマニフェスト
+    pub fn load(path: impl AsRef<Path>) -> Result<Manifest> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 64ビットのFNV-1aで`parts`をまとめてハッシュし、16進数にする。
+/// 区切りが変わっても同じ値にならないように、それぞれの長さも入れる
+pub fn hash(parts: &[&[u8]]) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    for part in parts {
+        for byte in (part.len() as u64).to_le_bytes().iter().chain(*part) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{hash, Manifest};
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(&[]), "cbf29ce484222325");
+        assert_eq!(
+            hash(&[b"markdown", b"template"]),
+            hash(&[b"markdown", b"template"])
+        );
+        assert_ne!(hash(&[b"ab", b"c"]), hash(&[b"a", b"bc"]));
+        assert_ne!(hash(&[b"markdown"]), hash(&[b"markdowm"]));
+    }
+
+    #[test]
+    fn test_load() {
+        let path =
+            std::env::temp_dir().join(format!("blog-parser-manifest-{}.json", std::process::id()));
+        assert_eq!(Manifest::load(&path).unwrap(), Manifest::default());
+
+        let mut manifest = Manifest::default();
+        manifest
+            .pages
+            .insert("hello".to_string(), hash(&[b"hello"]));
+        manifest.save(&path).unwrap();
+        assert_eq!(Manifest::load(&path).unwrap(), manifest);
+        fs::remove_file(path).unwrap();
+    }
+}
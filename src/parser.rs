@@ -1,249 +1,274 @@
-use crate::lexer::{Contents, ListTypes, RootTags, Text};
+use crate::lexer::{Contents, Li, ListTypes, RootTags};
 
-struct Parser {
-    html: String,
-}
+/// ノードごとのHTML出力。デフォルト実装が`HtmlRenderer`の出力なので、
+/// 変えたい要素のメソッドだけを上書きすればよい
+pub trait Renderer {
+    fn heading(&mut self, level: usize, text: &str) -> String {
+        format!("<h{0}>{1}</h{0}>\n", level, text)
+    }
+
+    /// `content`は描画済みのインライン要素
+    fn paragraph(&mut self, content: &str) -> String {
+        format!("<p>{}</p>\n", content)
+    }
+
+    /// `items`は描画済みの`list_item`を連結したもの
+    fn list(&mut self, list_type: ListTypes, indent: usize, items: &str) -> String {
+        format!(
+            "{0}<{1}>\n{2}{0}</{1}>\n",
+            "\t".repeat(indent),
+            list_type.to_string(),
+            items
+        )
+    }
+
+    /// `number`は同じ種類の項目が続く間の1始まりの番号。
+    /// `children`は入れ子になった描画済みの`list`(なければ空文字列)
+    fn list_item(
+        &mut self,
+        _list_type: ListTypes,
+        _number: usize,
+        indent: usize,
+        content: &str,
+        children: &str,
+    ) -> String {
+        if children.is_empty() {
+            format!("{}<li>{}</li>\n", "\t".repeat(indent), content)
+        } else {
+            format!(
+                "{0}<li>{1}\n{2}{0}</li>\n",
+                "\t".repeat(indent),
+                content,
+                children
+            )
+        }
+    }
+
+    fn code_block(&mut self, code: &str) -> String {
+        format!("<pre><code>{}</code></pre>\n", code)
+    }
+
+    fn text(&mut self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn bold(&mut self, text: &str) -> String {
+        format!("<b>{}</b>", text)
+    }
+
+    fn italic(&mut self, text: &str) -> String {
+        format!("<i>{}</i>", text)
+    }
 
-impl Parser {
-    fn push_html(&mut self, tag_name: &str, content: &str, new_line: bool) {
-        self.html.push_str(&format!(
-            "<{}>{}</{}>{}",
-            tag_name,
-            content,
-            tag_name,
-            if new_line { "\n" } else { "" }
-        ));
+    fn code(&mut self, code: &str) -> String {
+        format!("<code>{}</code>", code)
+    }
+
+    fn link(&mut self, href: &str, text: &str) -> String {
+        format!("<a href=\"{}\">{}</a>", href, text)
+    }
+
+    fn image(&mut self, src: &str, alt: &str) -> String {
+        format!("<img src=\"{}\" alt=\"{}\">", src, alt)
     }
 }
 
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {}
+
 pub fn parse(tags: Vec<RootTags>) -> String {
-    let mut parser = Parser {
-        html: String::new(),
-    };
+    render(&tags, &mut HtmlRenderer)
+}
+
+pub fn render<R: Renderer + ?Sized>(tags: &[RootTags], renderer: &mut R) -> String {
+    let mut html = String::new();
 
     for tag in tags {
         match tag {
-            RootTags::H1(h1) => parser.push_html("h1", &h1.0, true),
-            RootTags::H2(h2) => parser.push_html("h2", &h2.0, true),
-            RootTags::H3(h3) => parser.push_html("h3", &h3.0, true),
+            RootTags::H1(h1) => html.push_str(&renderer.heading(1, &h1.0)),
+            RootTags::H2(h2) => html.push_str(&renderer.heading(2, &h2.0)),
+            RootTags::H3(h3) => html.push_str(&renderer.heading(3, &h3.0)),
             RootTags::P(p) => {
-                let mut p_contents = String::new();
-                for content in &p.0 {
-                    match content {
-                        Contents::Text(text) => p_contents.push_str(&text.0),
-                        Contents::Code(code) => {
-                            p_contents.push_str(&format!("<code>{}</code>", code.0))
-                        }
-                        Contents::Bold(bold) => p_contents.push_str(&format!("<b>{}</b>", bold.0)),
-                        Contents::Italic(italic) => {
-                            p_contents.push_str(&format!("<i>{}</i>", italic.0))
-                        }
-                    }
-                }
-                parser.push_html("p", &p_contents, true)
+                let content = render_contents(renderer, &p.0);
+                html.push_str(&renderer.paragraph(&content))
+            }
+            RootTags::A(a) => {
+                let content = renderer.link(&a.href, &a.text);
+                html.push_str(&renderer.paragraph(&content))
+            }
+            RootTags::Img(img) => {
+                let content = renderer.image(&img.src, &img.alt);
+                html.push_str(&renderer.paragraph(&content))
             }
             RootTags::Li(lists) => {
-                let mut stack = Vec::<(usize, ListTypes)>::new();
-                stack.push((lists[0].indent, lists[0].list_type));
-                parser.html.push_str(&format!(
-                    "<{}>\n<li>{}",
-                    lists[0].list_type.to_string(),
-                    lists[0]
-                        .contents
-                        .iter()
-                        .fold(String::new(), |mut acc, content| {
-                            acc += &match content {
-                                Contents::Text(Text(text)) => text.to_string(),
-                                Contents::Code(code) => format!("<code>{}</code>", code.0),
-                                Contents::Bold(bold) => format!("<b>{}</b>", bold.0),
-                                Contents::Italic(italic) => format!("<i>{}</i>", italic.0),
-                            };
-                            acc
-                        })
-                ));
-
-                for li in lists.iter().skip(1) {
-                    while let Some(&(indent, list_type)) = stack.last() {
-                        match indent.cmp(&li.indent) {
-                            std::cmp::Ordering::Equal => {
-                                parser.html.push_str("</li>\n");
-                                break;
-                            }
-                            std::cmp::Ordering::Less => {
-                                parser.html.push_str(&format!(
-                                    "\n{}<{}>\n",
-                                    "\t".repeat(li.indent),
-                                    li.list_type.to_string(),
-                                ));
-                                stack.push((li.indent, li.list_type));
-                                break;
-                            }
-                            std::cmp::Ordering::Greater => {
-                                stack.pop();
-                                let &(second_last_indent, _) = stack.last().unwrap();
-                                parser.html.push_str(&format!(
-                                    "</li>\n{}</{}>\n{}",
-                                    "\t".repeat(indent),
-                                    list_type.to_string(),
-                                    "\t".repeat(second_last_indent)
-                                ));
-                            }
-                        }
-                    }
-                    parser.html.push_str(&format!(
-                        "{}<li>{}",
-                        "\t".repeat(li.indent),
-                        li.contents.iter().fold(String::new(), |mut acc, content| {
-                            acc += &match content {
-                                Contents::Text(Text(text)) => text.to_string(),
-                                Contents::Code(code) => format!("<code>{}</code>", code.0),
-                                Contents::Bold(bold) => format!("<b>{}</b>", bold.0),
-                                Contents::Italic(italic) => format!("<i>{}</i>", italic.0),
-                            };
-                            acc
-                        })
-                    ))
-                }
-                while let Some((indent, list_type)) = stack.pop() {
-                    parser.html.push_str(&format!(
-                        "</li>\n{}</{}>\n",
-                        "\t".repeat(indent),
-                        list_type.to_string()
-                    ));
+                let mut rest = &lists[..];
+                while !rest.is_empty() {
+                    let (list, consumed) = render_list(renderer, rest, rest[0].indent);
+                    html.push_str(&list);
+                    rest = &rest[consumed..];
                 }
             }
-            RootTags::Pre(pre) => parser
-                .html
-                .push_str(&format!("<pre><code>{}</code></pre>\n", pre.0)),
-            _ => panic!(),
+            RootTags::Pre(pre) => html.push_str(&renderer.code_block(&pre.0)),
         }
     }
 
-    parser.html
+    html
+}
+
+fn render_contents<R: Renderer + ?Sized>(renderer: &mut R, contents: &[Contents]) -> String {
+    contents.iter().fold(String::new(), |mut acc, content| {
+        acc += &match content {
+            Contents::Text(text) => renderer.text(&text.0),
+            Contents::Code(code) => renderer.code(&code.0),
+            Contents::Bold(bold) => renderer.bold(&bold.0),
+            Contents::Italic(italic) => renderer.italic(&italic.0),
+        };
+        acc
+    })
+}
+
+/// `lists[0]`と同じ階層に並ぶ項目を1つのリストとして描画し、消費した項目数を返す。
+/// 深い階層の項目は直前の項目の子リストになる。`root`は最上位リストのインデント
+fn render_list<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    lists: &[Li],
+    root: usize,
+) -> (String, usize) {
+    let indent = lists[0].indent;
+    let mut items = String::new();
+    let mut number = 0;
+    let mut previous = None;
+    let mut i = 0;
+    while i < lists.len() && lists[i].indent == indent {
+        let list_type = lists[i].list_type;
+        number = if previous == Some(list_type) {
+            number + 1
+        } else {
+            1
+        };
+        previous = Some(list_type);
+        let content = render_contents(renderer, &lists[i].contents);
+        i += 1;
+        let mut children = String::new();
+        while i < lists.len() && lists[i].indent > indent {
+            let (child, consumed) = render_list(renderer, &lists[i..], root);
+            children.push_str(&child);
+            i += consumed;
+        }
+        items.push_str(&renderer.list_item(list_type, number, indent - root, &content, &children));
+    }
+    (renderer.list(lists[0].list_type, indent - root, &items), i)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        lexer::{Bold, Code, Contents, Italic, Li, ListTypes, Pre, RootTags, Text, H1, H2, H3, P},
-        parser::parse,
+        lexer::{
+            Bold, Code, Contents, Italic, Li, ListTypes, Pre, RootTags, Span, Text, H1, H2, H3, P,
+        },
+        parser::{parse, render, HtmlRenderer, Renderer},
     };
 
+    fn text(text: &str) -> Contents {
+        Contents::Text(Text(text.to_string(), Span::default()))
+    }
+
+    fn italic(text: &str) -> Contents {
+        Contents::Italic(Italic(text.to_string(), Span::default()))
+    }
+
+    fn bold(text: &str) -> Contents {
+        Contents::Bold(Bold(text.to_string(), Span::default()))
+    }
+
+    fn code(text: &str) -> Contents {
+        Contents::Code(Code(text.to_string(), Span::default()))
+    }
+
+    fn p(contents: Vec<Contents>) -> RootTags {
+        RootTags::P(P(contents, Span::default()))
+    }
+
+    fn li(list_type: ListTypes, indent: usize, contents: Vec<Contents>) -> Li {
+        Li {
+            list_type,
+            indent,
+            contents,
+            span: Span::default(),
+        }
+    }
+
+    fn pre(code: &str) -> RootTags {
+        RootTags::Pre(Pre(code.to_string(), Span::default()))
+    }
+
     #[test]
     fn test_parse() {
+        let all = || {
+            vec![
+                text("テキスト"),
+                italic("イタリック"),
+                code("コード"),
+                bold("ボールド"),
+            ]
+        };
         let tests = [
             (
-                vec![RootTags::P(P(vec![
-                    Contents::Text(Text("テキスト".to_string())),
-                    Contents::Code(Code("コード".to_string())),
-                ]))],
+                vec![p(vec![text("テキスト"), code("コード")])],
                 "<p>テキスト<code>コード</code></p>\n",
             ),
             (
-                vec![RootTags::P(P(vec![Contents::Italic(Italic(
-                    "イタリック".to_string(),
-                ))]))],
+                vec![p(vec![italic("イタリック")])],
                 "<p><i>イタリック</i></p>\n",
             ),
+            (vec![p(vec![bold("ボールド")])], "<p><b>ボールド</b></p>\n"),
             (
-                vec![RootTags::P(P(vec![Contents::Bold(Bold(
-                    "ボールド".to_string(),
-                ))]))],
-                "<p><b>ボールド</b></p>\n",
-            ),
-            (
-                vec![RootTags::P(P(vec![
-                    Contents::Italic(Italic("イタリック".to_string())),
-                    Contents::Bold(Bold("ボールド".to_string())),
-                ]))],
+                vec![p(vec![italic("イタリック"), bold("ボールド")])],
                 "<p><i>イタリック</i><b>ボールド</b></p>\n",
             ),
             (
-                vec![RootTags::P(P(vec![
-                    Contents::Text(Text("テキスト".to_string())),
-                    Contents::Italic(Italic("イタリック".to_string())),
-                    Contents::Code(Code("コード".to_string())),
-                    Contents::Bold(Bold("ボールド".to_string())),
-                ]))],
+                vec![p(all())],
                 "<p>テキスト<i>イタリック</i><code>コード</code><b>ボールド</b></p>\n",
             ),
             (
-                vec![RootTags::H1(H1("見出し1".to_string()))],
+                vec![RootTags::H1(H1("見出し1".to_string(), Span::default()))],
                 "<h1>見出し1</h1>\n",
             ),
             (
-                vec![RootTags::H2(H2("見出し2".to_string()))],
+                vec![RootTags::H2(H2("見出し2".to_string(), Span::default()))],
                 "<h2>見出し2</h2>\n",
             ),
             (
-                vec![RootTags::H3(H3("見出し3".to_string()))],
+                vec![RootTags::H3(H3("見出し3".to_string(), Span::default()))],
                 "<h3>見出し3</h3>\n",
             ),
+            (vec![p(vec![text("段落")])], "<p>段落</p>\n"),
             (
-                vec![RootTags::P(P(vec![Contents::Text(Text(
-                    "段落".to_string(),
-                ))]))],
-                "<p>段落</p>\n",
-            ),
-            (
-                vec![RootTags::Li(vec![Li {
-                    list_type: ListTypes::Ul,
-                    indent: 0,
-                    contents: vec![Contents::Text(Text("リスト".to_string()))],
-                }])],
+                vec![RootTags::Li(vec![li(
+                    ListTypes::Ul,
+                    0,
+                    vec![text("リスト")],
+                )])],
                 "<ul>\n<li>リスト</li>\n</ul>\n",
             ),
             (
-                vec![RootTags::Li(vec![Li {
-                    list_type: ListTypes::Ol,
-                    indent: 0,
-                    contents: vec![Contents::Text(Text("リスト".to_string()))],
-                }])],
-                "<ol>\n<li>リスト</li>\n</ol>\n",
-            ),
-            (
-                vec![RootTags::Li(vec![Li {
-                    list_type: ListTypes::Ol,
-                    indent: 0,
-                    contents: vec![Contents::Text(Text("リスト".to_string()))],
-                }])],
+                vec![RootTags::Li(vec![li(
+                    ListTypes::Ol,
+                    0,
+                    vec![text("リスト")],
+                )])],
                 "<ol>\n<li>リスト</li>\n</ol>\n",
             ),
             (
-                vec![RootTags::Li(vec![Li {
-                    list_type: ListTypes::Ul,
-                    indent: 0,
-                    contents: vec![
-                        Contents::Text(Text("テキスト".to_string())),
-                        Contents::Italic(Italic("イタリック".to_string())),
-                        Contents::Code(Code("コード".to_string())),
-                        Contents::Bold(Bold("ボールド".to_string())),
-                    ],
-                }])],
-                "<ul>\n<li>テキスト<i>イタリック</i><code>コード</code><b>ボールド</b></li>\n</ul>\n",
+                vec![RootTags::Li(vec![li(ListTypes::Ul, 0, all())])],
+                "<ul>\n<li>テキスト<i>イタリック</i><code>コード</code><b>ボールド</b></li>\n\
+                 </ul>\n",
             ),
             (
                 vec![RootTags::Li(vec![
-                  Li {
-                    list_type: ListTypes::Ul,
-                    indent: 0,
-                    contents: vec![
-                        Contents::Text(Text("テキスト".to_string())),
-                        Contents::Italic(Italic("イタリック".to_string())),
-                        Contents::Code(Code("コード".to_string())),
-                        Contents::Bold(Bold("ボールド".to_string())),
-                    ],
-                },
-                  Li {
-                    list_type: ListTypes::Ul,
-                    indent: 2,
-                    contents: vec![
-                        Contents::Text(Text("テキスト".to_string())),
-                        Contents::Italic(Italic("イタリック".to_string())),
-                        Contents::Code(Code("コード".to_string())),
-                        Contents::Bold(Bold("ボールド".to_string())),
-                    ],
-                },
+                    li(ListTypes::Ul, 0, all()),
+                    li(ListTypes::Ul, 2, all()),
                 ])],
                 "<ul>
 <li>テキスト<i>イタリック</i><code>コード</code><b>ボールド</b>
@@ -254,28 +279,16 @@ mod tests {
 </ul>\n",
             ),
             (
-                vec![RootTags::Li(vec![Li {
-                    list_type: ListTypes::Ol,
-                    indent: 0,
-                    contents: vec![
-                        Contents::Text(Text("テキスト".to_string())),
-                        Contents::Italic(Italic("イタリック".to_string())),
-                        Contents::Code(Code("コード".to_string())),
-                        Contents::Bold(Bold("ボールド".to_string())),
-                    ],
-                }])],
-                "<ol>\n<li>テキスト<i>イタリック</i><code>コード</code><b>ボールド</b></li>\n</ol>\n",
+                vec![RootTags::Li(vec![li(ListTypes::Ol, 0, all())])],
+                "<ol>\n<li>テキスト<i>イタリック</i><code>コード</code><b>ボールド</b></li>\n\
+                 </ol>\n",
             ),
             (
-                vec![RootTags::Pre(Pre(
-                    "console.log('Hello, world!');\n".to_string()
-                ))],
+                vec![pre("console.log('Hello, world!');\n")],
                 "<pre><code>console.log('Hello, world!');\n</code></pre>\n",
             ),
             (
-                vec![RootTags::Pre(Pre(
-                    "const a = 1;\nconst b = 2;\nadd(a, b);\n".to_string(),
-                ))],
+                vec![pre("const a = 1;\nconst b = 2;\nadd(a, b);\n")],
                 "<pre><code>const a = 1;
 const b = 2;
 add(a, b);
@@ -284,36 +297,12 @@ add(a, b);
             ),
             (
                 vec![RootTags::Li(vec![
-                    Li {
-                        list_type: ListTypes::Ul,
-                        indent: 0,
-                        contents: vec![Contents::Text(Text("リスト1".to_string()))],
-                    },
-                    Li {
-                        list_type: ListTypes::Ul,
-                        indent: 1,
-                        contents: vec![Contents::Text(Text("リスト1-1".to_string()))],
-                    },
-                    Li {
-                        list_type: ListTypes::Ul,
-                        indent: 1,
-                        contents: vec![Contents::Text(Text("リスト1-2".to_string()))],
-                    },
-                    Li {
-                        list_type: ListTypes::Ul,
-                        indent: 0,
-                        contents: vec![Contents::Text(Text("リスト2".to_string()))],
-                    },
-                    Li {
-                        list_type: ListTypes::Ol,
-                        indent: 1,
-                        contents: vec![Contents::Text(Text("リスト2-1".to_string()))],
-                    },
-                    Li {
-                        list_type: ListTypes::Ol,
-                        indent: 1,
-                        contents: vec![Contents::Text(Text("リスト2-2".to_string()))],
-                    },
+                    li(ListTypes::Ul, 0, vec![text("リスト1")]),
+                    li(ListTypes::Ul, 1, vec![text("リスト1-1")]),
+                    li(ListTypes::Ul, 1, vec![text("リスト1-2")]),
+                    li(ListTypes::Ul, 0, vec![text("リスト2")]),
+                    li(ListTypes::Ol, 1, vec![text("リスト2-1")]),
+                    li(ListTypes::Ol, 1, vec![text("リスト2-2")]),
                 ])],
                 "<ul>
 <li>リスト1
@@ -333,51 +322,23 @@ add(a, b);
             ),
             (
                 vec![
-                    RootTags::H1(H1("見出し1".to_string())),
-                    RootTags::H2(H2("見出し2".to_string())),
-                    RootTags::P(P(vec![Contents::Text(Text("段落1".to_string()))])),
-                    RootTags::H3(H3("見出し3".to_string())),
-                    RootTags::P(P(vec![Contents::Text(Text("段落2".to_string()))])),
-                    RootTags::Pre(Pre("console.log('Hello, world!');\n".to_string())),
+                    RootTags::H1(H1("見出し1".to_string(), Span::default())),
+                    RootTags::H2(H2("見出し2".to_string(), Span::default())),
+                    p(vec![text("段落1")]),
+                    RootTags::H3(H3("見出し3".to_string(), Span::default())),
+                    p(vec![text("段落2")]),
+                    pre("console.log('Hello, world!');\n"),
                     RootTags::Li(vec![
-                        Li {
-                            list_type: ListTypes::Ul,
-                            indent: 0,
-                            contents: vec![Contents::Text(Text("リスト1".to_string()))],
-                        },
-                        Li {
-                            list_type: ListTypes::Ul,
-                            indent: 0,
-                            contents: vec![Contents::Text(Text("リスト2".to_string()))],
-                        },
-                        Li {
-                            list_type: ListTypes::Ul,
-                            indent: 1,
-                            contents: vec![Contents::Text(Text("リスト2-1".to_string()))],
-                        },
-                        Li {
-                            list_type: ListTypes::Ul,
-                            indent: 1,
-                            contents: vec![Contents::Text(Text("リスト2-2".to_string()))],
-                        },
-                        Li {
-                            list_type: ListTypes::Ul,
-                            indent: 2,
-                            contents: vec![Contents::Text(Text("リスト2-2-1".to_string()))],
-                        },
-                        Li {
-                            list_type: ListTypes::Ul,
-                            indent: 1,
-                            contents: vec![Contents::Text(Text("リスト2-3".to_string()))],
-                        },
-                        Li {
-                            list_type: ListTypes::Ul,
-                            indent: 0,
-                            contents: vec![Contents::Text(Text("リスト3".to_string()))],
-                        },
+                        li(ListTypes::Ul, 0, vec![text("リスト1")]),
+                        li(ListTypes::Ul, 0, vec![text("リスト2")]),
+                        li(ListTypes::Ul, 1, vec![text("リスト2-1")]),
+                        li(ListTypes::Ul, 1, vec![text("リスト2-2")]),
+                        li(ListTypes::Ul, 2, vec![text("リスト2-2-1")]),
+                        li(ListTypes::Ul, 1, vec![text("リスト2-3")]),
+                        li(ListTypes::Ul, 0, vec![text("リスト3")]),
                     ]),
-                    RootTags::Pre(Pre("console.log('Hello, world!');\n".to_string())),
-                    RootTags::P(P(vec![Contents::Text(Text("段落3".to_string()))])),
+                    pre("console.log('Hello, world!');\n"),
+                    p(vec![text("段落3")]),
                 ],
                 "<h1>見出し1</h1>
 <h2>見出し2</h2>
@@ -412,4 +373,30 @@ add(a, b);
             assert_eq!(parse(input), html);
         }
     }
+
+    #[test]
+    fn test_render_override() {
+        struct CopyButtonRenderer;
+
+        impl Renderer for CopyButtonRenderer {
+            fn code_block(&mut self, code: &str) -> String {
+                format!(
+                    "<div class=\"copy\">{}</div>\n",
+                    HtmlRenderer.code_block(code).trim_end()
+                )
+            }
+        }
+
+        let tags = vec![
+            RootTags::P(P(
+                vec![Contents::Code(Code("コード".to_string(), Span::default()))],
+                Span::default(),
+            )),
+            RootTags::Pre(Pre("let a = 1;\n".to_string(), Span::default())),
+        ];
+        assert_eq!(
+            render(&tags, &mut CopyButtonRenderer),
+            "<p><code>コード</code></p>\n<div class=\"copy\"><pre><code>let a = 1;\n</code></pre></div>\n"
+        );
+    }
 }
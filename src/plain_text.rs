@@ -0,0 +1,254 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    lexer::{tokenize_contents, Contents, ListTypes, RootTags, Span},
+    parser::{render, Renderer},
+};
+
+/// マークアップを取り除いたテキストを出力する。
+/// `bullets`がtrueならリスト項目の先頭に`-`や番号を付け、子リストを字下げする
+pub struct PlainTextRenderer {
+    pub bullets: bool,
+}
+
+impl Renderer for PlainTextRenderer {
+    fn heading(&mut self, _level: usize, text: &str) -> String {
+        format!("{}\n", text)
+    }
+
+    fn paragraph(&mut self, content: &str) -> String {
+        format!("{}\n", content)
+    }
+
+    fn list(&mut self, _list_type: ListTypes, _indent: usize, items: &str) -> String {
+        items.to_string()
+    }
+
+    fn list_item(
+        &mut self,
+        list_type: ListTypes,
+        number: usize,
+        _indent: usize,
+        content: &str,
+        children: &str,
+    ) -> String {
+        if !self.bullets {
+            return format!("{}\n{}", content, children);
+        }
+        let marker = match list_type {
+            ListTypes::Ul => "-".to_string(),
+            ListTypes::Ol => format!("{}.", number),
+        };
+        let children = children
+            .lines()
+            .map(|line| format!("  {}\n", line))
+            .collect::<String>();
+        format!("{} {}\n{}", marker, content, children)
+    }
+
+    fn code_block(&mut self, code: &str) -> String {
+        code.to_string()
+    }
+
+    fn text(&mut self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn bold(&mut self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn italic(&mut self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn code(&mut self, code: &str) -> String {
+        code.to_string()
+    }
+
+    fn link(&mut self, _href: &str, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn image(&mut self, _src: &str, alt: &str) -> String {
+        alt.to_string()
+    }
+}
+
+pub fn to_plain_text(tags: &[RootTags], bullets: bool) -> String {
+    render(tags, &mut PlainTextRenderer { bullets })
+}
+
+/// 見出しの`**`などを取り除いたテキスト
+pub fn heading_text(text: &str) -> String {
+    tokenize_contents(text, Span::default())
+        .into_iter()
+        .map(|content| match content {
+            Contents::Text(text) => text.0,
+            Contents::Bold(bold) => bold.0,
+            Contents::Italic(italic) => italic.0,
+            Contents::Code(code) => code.0,
+        })
+        .collect()
+}
+
+/// 見出しを除いた本文を空白で1行につなげ、`length`文字に切り詰める
+pub fn excerpt(tags: &[RootTags], length: usize) -> String {
+    let mut renderer = PlainTextRenderer { bullets: false };
+    let text = tags
+        .iter()
+        .filter(|tag| !matches!(tag, RootTags::H1(_) | RootTags::H2(_) | RootTags::H3(_)))
+        .map(|tag| render(std::slice::from_ref(tag), &mut renderer))
+        .collect::<String>();
+    truncate(
+        &text.split_whitespace().collect::<Vec<_>>().join(" "),
+        length,
+    )
+}
+
+/// 空白以外の文字数(書記素単位)
+pub fn char_count(text: &str) -> usize {
+    text.graphemes(true)
+        .filter(|grapheme| !grapheme.trim().is_empty())
+        .count()
+}
+
+/// 1分あたりに読める日本語などの文字数
+pub const CJK_CHARS_PER_MINUTE: usize = 500;
+/// 1分あたりに読める英語などの単語数
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// 読了時間(分)。日本語などの文字は文字数、それ以外は単語数で見積もって合計する
+pub fn reading_time(text: &str) -> usize {
+    let cjk_chars = text.chars().filter(|&c| is_cjk(c)).count();
+    let words = text
+        .split(|c: char| c.is_whitespace() || is_cjk(c))
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count();
+    let minutes =
+        cjk_chars as f64 / CJK_CHARS_PER_MINUTE as f64 + words as f64 / WORDS_PER_MINUTE as f64;
+    if minutes == 0.0 {
+        0
+    } else {
+        (minutes.ceil() as usize).max(1)
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3000}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF00}'..='\u{FFEF}'
+            | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+/// 書記素単位で`length`文字に切り詰め、切り詰めた場合は末尾に`…`を付ける
+pub fn truncate(text: &str, length: usize) -> String {
+    let mut graphemes = text.graphemes(true);
+    let truncated = graphemes.by_ref().take(length).collect::<String>();
+    if graphemes.next().is_some() {
+        truncated + "…"
+    } else {
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::tokenize,
+        plain_text::{char_count, excerpt, heading_text, reading_time, to_plain_text, truncate},
+    };
+
+    #[test]
+    fn test_to_plain_text() {
+        let tags = tokenize(
+            "# 見出し1
+段落*イタリック*`コード`**ボールド**
+
+- リスト1
+  1. リスト1-1
+  1. リスト1-2
+- リスト2
+
+```
+let a = 1;
+```
+",
+        );
+        assert_eq!(
+            to_plain_text(&tags, false),
+            "見出し1
+段落イタリックコードボールド
+リスト1
+リスト1-1
+リスト1-2
+リスト2
+let a = 1;
+"
+        );
+        assert_eq!(
+            to_plain_text(&tags, true),
+            "見出し1
+段落イタリックコードボールド
+- リスト1
+  1. リスト1-1
+  2. リスト1-2
+- リスト2
+let a = 1;
+"
+        );
+    }
+
+    #[test]
+    fn test_heading_text() {
+        assert_eq!(heading_text("Rustの**所有権**と`Box`"), "Rustの所有権とBox");
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let tags = tokenize("# 見出し1\n段落1\n\n段落**2**\n");
+        assert_eq!(excerpt(&tags, 100), "段落1 段落2");
+        assert_eq!(excerpt(&tags, 3), "段落1…");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("日本語のテキスト", 3), "日本語…");
+        assert_eq!(truncate("日本語", 3), "日本語");
+        // 濁点の結合文字や絵文字の途中では切らない
+        assert_eq!(
+            truncate("か\u{3099}き\u{3099}く", 2),
+            "か\u{3099}き\u{3099}…"
+        );
+        assert_eq!(truncate("👨‍👩‍👧家族", 1), "👨‍👩‍👧…");
+    }
+
+    #[test]
+    fn test_char_count() {
+        assert_eq!(char_count("日本語 の\nテキスト"), 8);
+        assert_eq!(char_count("か\u{3099}き"), 2);
+    }
+
+    #[test]
+    fn test_reading_time() {
+        assert_eq!(reading_time(""), 0);
+        assert_eq!(reading_time("短い"), 1);
+        assert_eq!(reading_time(&"あ".repeat(1000)), 2);
+        assert_eq!(reading_time(&"word ".repeat(400)), 2);
+        // 500文字で1分、200語で1分の合計
+        assert_eq!(
+            reading_time(&format!("{}{}", "あ".repeat(500), "Rust ".repeat(200))),
+            2
+        );
+        assert_eq!(
+            reading_time(&format!("{}{}", "あ".repeat(501), "Rust ".repeat(200))),
+            3
+        );
+    }
+}
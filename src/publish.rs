@@ -0,0 +1,125 @@
+use chrono::{DateTime, FixedOffset};
+
+use crate::{error::Error, feed::parse_date, Rendered, Result};
+
+/// front matterの`draft`と`publish_at`から決まる記事の公開状態
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    Published,
+    /// `draft: true`
+    Draft,
+    /// `publish_at`がまだ来ていない
+    Scheduled(DateTime<FixedOffset>),
+}
+
+impl Status {
+    /// `publish_at`は`2024-04-01`(`now`のタイムゾーンの0時)かRFC 3339の日時
+    pub fn of(rendered: &Rendered, now: DateTime<FixedOffset>) -> Result<Status> {
+        if rendered
+            .front_matter
+            .get("draft")
+            .is_some_and(|draft| draft == "true")
+        {
+            return Ok(Status::Draft);
+        }
+        let Some(publish_at) = rendered.front_matter.get("publish_at") else {
+            return Ok(Status::Published);
+        };
+        match parse_date(publish_at, *now.offset()) {
+            Some(publish_at) if publish_at > now => Ok(Status::Scheduled(publish_at)),
+            Some(_) => Ok(Status::Published),
+            None => Err(Error::InvalidPublishAt(publish_at.clone())),
+        }
+    }
+
+    pub fn is_published(&self) -> bool {
+        *self == Status::Published
+    }
+}
+
+/// プレビューで公開前の記事だとわかるように、`<body>`の直後(なければ先頭)に入れる帯
+pub fn insert_banner(html: &str, status: &Status) -> String {
+    let message = match status {
+        Status::Published => return html.to_string(),
+        Status::Draft => "Draft: this post is not published".to_string(),
+        Status::Scheduled(publish_at) => format!(
+            "Scheduled: this post will be published at {}",
+            publish_at.format("%Y-%m-%d %H:%M")
+        ),
+    };
+    let banner = format!(
+        "<div class=\"preview-banner\" style=\"background:#fde68a;color:#000;padding:0.5em;text-align:center;font-weight:bold\">{}</div>\n",
+        message
+    );
+    let body = html
+        .find("<body")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+    match body {
+        Some(i) => format!("{}{}{}", &html[..i], banner, &html[i..]),
+        None => format!("{}{}", banner, html),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset};
+
+    use super::{insert_banner, Status};
+    use crate::{error::Error, markdown_to_html, Options};
+
+    fn now() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2024-04-01T12:00:00+09:00").unwrap()
+    }
+
+    fn status(front_matter: &str) -> crate::Result<Status> {
+        let markdown = format!("---\ntitle: タイトル\n{}---\n段落\n", front_matter);
+        Status::of(
+            &markdown_to_html(&markdown, &Options::default()).unwrap(),
+            now(),
+        )
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(status("").unwrap(), Status::Published);
+        assert_eq!(status("draft: true\n").unwrap(), Status::Draft);
+        assert_eq!(status("draft: false\n").unwrap(), Status::Published);
+        assert_eq!(
+            status("publish_at: 2024-04-01\n").unwrap(),
+            Status::Published
+        );
+        assert_eq!(
+            status("publish_at: 2024-04-02\n").unwrap(),
+            Status::Scheduled(DateTime::parse_from_rfc3339("2024-04-02T00:00:00+09:00").unwrap())
+        );
+        assert!(status("publish_at: 2024-04-01T12:30:00+09:00\n")
+            .unwrap()
+            .ne(&Status::Published));
+        assert_eq!(
+            status("draft: true\npublish_at: 2024-01-01\n").unwrap(),
+            Status::Draft
+        );
+        assert!(matches!(
+            status("publish_at: 来週\n"),
+            Err(Error::InvalidPublishAt(value)) if value == "来週"
+        ));
+    }
+
+    #[test]
+    fn test_insert_banner() {
+        assert_eq!(
+            insert_banner("<body><p>本文</p></body>", &Status::Published),
+            "<body><p>本文</p></body>"
+        );
+        let html = insert_banner(
+            "<html><body class=\"post\"><p>本文</p></body>",
+            &Status::Draft,
+        );
+        assert!(html.starts_with("<html><body class=\"post\"><div class=\"preview-banner\""));
+        assert!(html.ends_with("Draft: this post is not published</div>\n<p>本文</p></body>"));
+
+        let html = insert_banner("<p>本文</p>", &Status::Scheduled(now()));
+        assert!(html.starts_with("<div class=\"preview-banner\""));
+        assert!(html.contains("published at 2024-04-01 12:00</div>"));
+    }
+}
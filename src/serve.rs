@@ -0,0 +1,255 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use crate::error::Result;
+
+/// ライブリロードのスクリプトがビルドの番号を問い合わせるパス
+pub const RELOAD_PATH: &str = "/__livereload";
+
+/// ビルドの番号が描画したときから変わったらページを読み直すスクリプト
+const RELOAD_SCRIPT: &str = r#"<script>
+(() => {
+  const version = "{version}";
+  setInterval(async () => {
+    try {
+      const response = await fetch("{path}", { cache: "no-store" });
+      if ((await response.text()) !== version) location.reload();
+    } catch (_) {}
+  }, 1000);
+})();
+</script>
+"#;
+
+/// `root`のファイルを返すプレビュー用のHTTP/1.1サーバー。
+/// HTMLにはライブリロードのスクリプトを差し込む
+pub struct Server {
+    listener: TcpListener,
+    root: PathBuf,
+    version: Arc<AtomicU64>,
+}
+
+/// ビルドの後にブラウザへ読み直しを知らせる
+#[derive(Clone)]
+pub struct Reloader {
+    version: Arc<AtomicU64>,
+}
+
+impl Reloader {
+    pub fn reload(&self) {
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Server {
+    /// ポートが0なら空いているポートを使う
+    pub fn bind(addr: impl ToSocketAddrs, root: impl Into<PathBuf>) -> Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            root: root.into(),
+            version: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn reloader(&self) -> Reloader {
+        Reloader {
+            version: Arc::clone(&self.version),
+        }
+    }
+
+    /// 接続ごとにスレッドを立てて応答する。戻らない
+    pub fn run(self) -> Result<()> {
+        let root = Arc::new(self.root);
+        for stream in self.listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let root = Arc::clone(&root);
+            let version = Arc::clone(&self.version);
+            thread::spawn(move || {
+                // 接続ごとのエラーはブラウザ側の切断などなので無視する
+                let _ = handle(stream, &root, version.load(Ordering::SeqCst));
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(status: &'static str, body: &str) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.as_bytes().to_vec(),
+        }
+    }
+}
+
+fn handle(stream: TcpStream, root: &Path, version: u64) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // ヘッダーは使わないので読み飛ばす
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut words = request_line.split_whitespace();
+    let (method, target) = (
+        words.next().unwrap_or_default(),
+        words.next().unwrap_or("/"),
+    );
+    let response = match method {
+        "GET" | "HEAD" => respond(root, target, version),
+        _ => Response::text("405 Method Not Allowed", "method not allowed\n"),
+    };
+
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()
+}
+
+fn respond(root: &Path, target: &str, version: u64) -> Response {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    if path == RELOAD_PATH {
+        return Response::text("200 OK", &version.to_string());
+    }
+    let Some(relative) = url_decode(path).and_then(|path| safe_path(&path)) else {
+        return Response::text("400 Bad Request", "bad request\n");
+    };
+
+    let mut file = root.join(relative);
+    if file.is_dir() {
+        file = file.join("index.html");
+    }
+    let Ok(body) = fs::read(&file) else {
+        return Response::text("404 Not Found", "not found\n");
+    };
+    let content_type = content_type(&file);
+    let body = if content_type.starts_with("text/html") {
+        inject_reload_script(&String::from_utf8_lossy(&body), version).into_bytes()
+    } else {
+        body
+    };
+    Response {
+        status: "200 OK",
+        content_type,
+        body,
+    }
+}
+
+/// `..`などを含まない、`root`からの相対パス
+fn safe_path(path: &str) -> Option<PathBuf> {
+    let relative = Path::new(path.trim_start_matches('/'));
+    relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| relative.to_path_buf())
+}
+
+fn url_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// `</body>`の前に、なければ最後にスクリプトを入れる
+pub fn inject_reload_script(html: &str, version: u64) -> String {
+    let script = RELOAD_SCRIPT
+        .replace("{version}", &version.to_string())
+        .replace("{path}", RELOAD_PATH);
+    match html.rfind("</body>") {
+        Some(i) => format!("{}{}{}", &html[..i], script, &html[i..]),
+        None => format!("{}{}", html, script),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{inject_reload_script, safe_path, url_decode};
+
+    #[test]
+    fn test_inject_reload_script() {
+        let html = inject_reload_script("<body><p>本文</p></body></html>", 3);
+        assert!(html.starts_with("<body><p>本文</p><script>"));
+        assert!(html.ends_with("</script>\n</body></html>"));
+        assert!(html.contains("const version = \"3\";"));
+        assert!(inject_reload_script("<p>本文</p>", 0).starts_with("<p>本文</p><script>"));
+    }
+
+    #[test]
+    fn test_safe_path() {
+        assert_eq!(safe_path("/"), Some(PathBuf::new()));
+        assert_eq!(safe_path("/2024/rust/"), Some(PathBuf::from("2024/rust")));
+        assert_eq!(safe_path("/../secret"), None);
+        assert_eq!(safe_path("/a/../../secret"), None);
+    }
+
+    #[test]
+    fn test_url_decode() {
+        assert_eq!(
+            url_decode("/tags/%E5%85%A5%E9%96%80/").as_deref(),
+            Some("/tags/入門/")
+        );
+        assert_eq!(url_decode("/%2e%2e/").as_deref(), Some("/../"));
+        assert_eq!(url_decode("/%zz"), None);
+        assert_eq!(url_decode("/%e5"), None);
+    }
+}
@@ -0,0 +1,140 @@
+use std::{collections::VecDeque, fs};
+
+use crate::{
+    config::Config,
+    data::Data,
+    error::Result,
+    feed::post_url,
+    index::{newest_first, page_url},
+    template::filter::escape_html,
+};
+
+/// 記事の一覧にある(公開した)記事と、`index_pages`ページの一覧のURLを並べた`sitemap.xml`。
+/// `lastmod`は記事の更新日、一覧はそのページの記事で一番新しい更新日
+pub fn sitemap(config: &Config, data: &VecDeque<Data>, index_pages: usize) -> String {
+    let base_url = config.base_url.trim_end_matches('/');
+    let posts = newest_first(data);
+    let chunks = posts.chunks(config.per_page.max(1)).collect::<Vec<_>>();
+
+    let mut urls = (1..=index_pages)
+        .map(|page| {
+            let lastmod = chunks
+                .get(page - 1)
+                .and_then(|posts| posts.iter().map(|post| post.updated_at()).max());
+            (format!("{}{}", base_url, page_url(page)), lastmod)
+        })
+        .collect::<Vec<_>>();
+    urls.extend(
+        posts
+            .iter()
+            .map(|post| (post_url(base_url, &post.slug), Some(post.updated_at()))),
+    );
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (url, lastmod) in urls {
+        xml.push_str(&format!("<url><loc>{}</loc>", escape_html(&url)));
+        if let Some(lastmod) = lastmod {
+            xml.push_str(&format!("<lastmod>{}</lastmod>", escape_html(lastmod)));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// 設定の`robots_txt`の最後に`sitemap.xml`の場所を足したもの
+pub fn robots(config: &Config) -> String {
+    let mut robots = config.robots_txt.trim_end().to_string();
+    if !robots.is_empty() {
+        robots.push_str("\n\n");
+    }
+    robots.push_str(&format!(
+        "Sitemap: {}/sitemap.xml\n",
+        config.base_url.trim_end_matches('/')
+    ));
+    robots
+}
+
+/// `sitemap.xml`と`robots.txt`を書き出す。`base_url`がなければ何もしない
+pub fn write_sitemap(config: &Config, data: &VecDeque<Data>, index_pages: usize) -> Result<bool> {
+    if config.base_url.is_empty() {
+        return Ok(false);
+    }
+    fs::create_dir_all(&config.output_dir)?;
+    fs::write(
+        config.output_dir.join("sitemap.xml"),
+        sitemap(config, data, index_pages),
+    )?;
+    fs::write(config.output_dir.join("robots.txt"), robots(config))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::{robots, sitemap};
+    use crate::{config::Config, data::Data};
+
+    fn data(slug: &str, created_at: &str) -> Data {
+        Data {
+            slug: slug.to_string(),
+            title: slug.to_string(),
+            created_at: created_at.to_string(),
+            reading_time: 1,
+            char_count: 100,
+            ..Data::default()
+        }
+    }
+
+    #[test]
+    fn test_sitemap() {
+        let config = Config {
+            base_url: "https://example.com/".to_string(),
+            per_page: 2,
+            ..Config::default()
+        };
+        let data = VecDeque::from([
+            data("c", "2024-05-01"),
+            data("2024/b c", "2024-04-01"),
+            Data {
+                updated_at: "2024-06-01".to_string(),
+                ..data("a&b", "2024-03-01")
+            },
+        ]);
+        assert_eq!(
+            sitemap(&config, &data, 2),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">
+<url><loc>https://example.com/</loc><lastmod>2024-05-01</lastmod></url>
+<url><loc>https://example.com/page/2/</loc><lastmod>2024-06-01</lastmod></url>
+<url><loc>https://example.com/c/</loc><lastmod>2024-05-01</lastmod></url>
+<url><loc>https://example.com/2024/b%20c/</loc><lastmod>2024-04-01</lastmod></url>
+<url><loc>https://example.com/a%26b/</loc><lastmod>2024-06-01</lastmod></url>
+</urlset>
+"
+        );
+        assert!(!sitemap(&config, &data, 0).contains("/page/"));
+    }
+
+    #[test]
+    fn test_robots() {
+        let config = Config {
+            base_url: "https://example.com/blog".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(
+            robots(&config),
+            "User-agent: *\nAllow: /\n\nSitemap: https://example.com/blog/sitemap.xml\n"
+        );
+        let config = Config {
+            robots_txt: "User-agent: *\nDisallow: /private/\n\n".to_string(),
+            ..config
+        };
+        assert_eq!(
+            robots(&config),
+            "User-agent: *\nDisallow: /private/\n\nSitemap: https://example.com/blog/sitemap.xml\n"
+        );
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    config::Config,
+    data::Data,
+    error::{Error, Result},
+    index::newest_first,
+    manifest::Manifest,
+    template::{cache::TemplateCache, filter::url_encode, Context, ToValue, Value},
+};
+
+/// front matterの`tags: Rust, 入門`か`tags: [Rust, 入門]`。空のタグと重複は除く
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .unwrap_or(text);
+    let mut tags = Vec::new();
+    for tag in text.split(',').map(str::trim) {
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// 出力先のディレクトリ名にするタグの名前。`tag_slugs`になければタグ(`/`は`-`にする)
+pub fn tag_slug(config: &Config, tag: &str) -> String {
+    config
+        .tag_slugs
+        .get(tag)
+        .cloned()
+        .unwrap_or_else(|| tag.replace(['/', '\\'], "-"))
+}
+
+/// `/tags/<slug>/`。日本語などはパーセントエンコードする
+pub fn tag_url(slug: &str) -> String {
+    format!("/tags/{}/", url_encode(slug))
+}
+
+/// テンプレートで使う`$tag.name`・`$tag.slug`・`$tag.url`
+pub fn tag_value(config: &Config, tag: &str) -> BTreeMap<String, Value> {
+    let slug = tag_slug(config, tag);
+    BTreeMap::from([
+        ("name".to_string(), tag.to_value()),
+        ("url".to_string(), tag_url(&slug).to_value()),
+        ("slug".to_string(), slug.to_value()),
+    ])
+}
+
+/// 記事の`to_value`の`$post.tags`を、名前だけでなくURLも持つ`tag_value`にしたもの
+pub fn post_value(config: &Config, post: &Data) -> Value {
+    let mut value = post.to_value();
+    if let Value::Map(map) = &mut value {
+        let tags = post
+            .tags
+            .iter()
+            .map(|tag| tag_value(config, tag))
+            .collect::<Vec<_>>();
+        map.insert("tags".to_string(), tags.to_value());
+    }
+    value
+}
+
+/// タグ(名前順)ごとの、作成日の新しい順の記事
+fn group(data: &VecDeque<Data>) -> BTreeMap<&str, Vec<&Data>> {
+    let mut groups = BTreeMap::<&str, Vec<&Data>>::new();
+    for post in newest_first(data) {
+        for tag in &post.tags {
+            groups.entry(tag).or_default().push(post);
+        }
+    }
+    groups
+}
+
+/// タグのslugがそれぞれ`tags/`の下の1つのディレクトリ名になり、ほかのタグと重ならないことを確かめる
+fn check_slugs<'a>(config: &Config, tags: impl Iterator<Item = &'a str>) -> Result<()> {
+    // 大文字と小文字を区別しないファイルシステムでも重ならないようにする
+    let mut dirs = BTreeMap::<String, &str>::new();
+    for tag in tags {
+        let slug = tag_slug(config, tag);
+        let mut components = Path::new(&slug).components();
+        let single = matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(name)), None) if name == slug.as_str()
+        );
+        if !single {
+            return Err(Error::InvalidTagSlug {
+                tag: tag.to_string(),
+                slug,
+            });
+        }
+        if let Some(other) = dirs.insert(slug.to_lowercase(), tag) {
+            return Err(Error::DuplicateTagSlug {
+                tags: (other.to_string(), tag.to_string()),
+                slug,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// タグごとの記事の一覧のページの出力先と変数(`$tag`・`$posts`・`$count`)。
+/// `.`や`..`などディレクトリ名にできないslugや、ほかのタグと同じslugのタグがあればエラー
+pub fn tag_pages(config: &Config, data: &VecDeque<Data>) -> Result<Vec<(PathBuf, Context)>> {
+    let groups = group(data);
+    check_slugs(config, groups.keys().copied())?;
+    Ok(groups
+        .into_iter()
+        .map(|(tag, posts)| {
+            let path = Path::new("tags")
+                .join(tag_slug(config, tag))
+                .join("index.html");
+            let posts = posts
+                .iter()
+                .map(|post| post_value(config, post))
+                .collect::<Vec<_>>();
+
+            let mut context = Context::new();
+            context.insert("tag".to_string(), tag_value(config, tag).to_value());
+            context.insert("count".to_string(), posts.len().to_string().to_value());
+            context.insert("posts".to_string(), posts.to_value());
+            (path, context)
+        })
+        .collect())
+}
+
+/// タグの一覧のページの変数。`$tags`は記事の多い順(同じ数なら名前順)で、`$tag.count`は記事数
+pub fn tag_cloud(config: &Config, data: &VecDeque<Data>) -> Context {
+    let mut groups = group(data).into_iter().collect::<Vec<_>>();
+    groups.sort_by_key(|(_, posts)| Reverse(posts.len()));
+    let tags = groups
+        .into_iter()
+        .map(|(tag, posts)| {
+            let mut value = tag_value(config, tag);
+            value.insert("count".to_string(), posts.len().to_string().to_value());
+            value
+        })
+        .collect::<Vec<_>>();
+
+    let mut context = Context::new();
+    context.insert("tags".to_string(), tags.to_value());
+    context
+}
+
+/// `tag_template`でタグごとの記事の一覧を、`tag_cloud_template`でタグの一覧を書き出し、
+/// 書き出したタグごとのページ数を返す。テンプレートがなければそのページは書き出さない。
+/// `content/tags/howto.md`のような記事の出力を消さないように、`manifest`にある前回のページのうち
+/// 今回書き出さなかったものだけを消す
+pub fn write_tags(
+    config: &Config,
+    cache: &mut TemplateCache,
+    data: &VecDeque<Data>,
+    manifest: &mut Manifest,
+) -> Result<usize> {
+    let tag_pages = tag_pages(config, data)?;
+
+    let mut written = BTreeSet::new();
+    if config.tag_template.exists() {
+        let template = cache.get(&config.tag_template)?;
+        for (path, context) in tag_pages {
+            write_page(config, &path, &template.render(&context))?;
+            written.insert(path);
+        }
+    }
+    let pages = written.len();
+    if config.tag_cloud_template.exists() {
+        let template = cache.get(&config.tag_cloud_template)?;
+        let path = Path::new("tags").join("index.html");
+        write_page(config, &path, &template.render(&tag_cloud(config, data)))?;
+        written.insert(path);
+    }
+
+    // 記事からなくなったタグのページが残らないようにする
+    for path in manifest.tags.difference(&written) {
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            continue;
+        }
+        let path = config.output_dir.join(path);
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        for dir in path.ancestors().skip(1) {
+            if dir == config.output_dir || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    manifest.tags = written;
+    Ok(pages)
+}
+
+fn write_page(config: &Config, path: &Path, html: &str) -> Result<()> {
+    let path = config.output_dir.join(path);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, html)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, VecDeque},
+        fs,
+        path::PathBuf,
+    };
+
+    use super::{parse_tags, tag_cloud, tag_pages, tag_slug, tag_url, write_tags};
+    use crate::{
+        config::Config,
+        data::Data,
+        error::Error,
+        manifest::Manifest,
+        template::{cache::TemplateCache, Template},
+    };
+
+    fn data(slug: &str, created_at: &str, tags: &[&str]) -> Data {
+        Data {
+            slug: slug.to_string(),
+            title: slug.to_uppercase(),
+            created_at: created_at.to_string(),
+            reading_time: 1,
+            char_count: 100,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Data::default()
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            tag_slugs: BTreeMap::from([("入門".to_string(), "nyumon".to_string())]),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags("Rust, 入門"), ["Rust", "入門"]);
+        assert_eq!(parse_tags(" [Rust,,入門 , Rust] "), ["Rust", "入門"]);
+        assert!(parse_tags("").is_empty());
+        assert!(parse_tags("[]").is_empty());
+    }
+
+    #[test]
+    fn test_tag_slug() {
+        let config = config();
+        assert_eq!(tag_slug(&config, "入門"), "nyumon");
+        assert_eq!(tag_slug(&config, "日記"), "日記");
+        assert_eq!(tag_slug(&config, "CI/CD"), "CI-CD");
+        assert_eq!(tag_url("nyumon"), "/tags/nyumon/");
+        assert_eq!(tag_url("日記"), "/tags/%E6%97%A5%E8%A8%98/");
+        assert_eq!(tag_url("C++"), "/tags/C%2B%2B/");
+    }
+
+    #[test]
+    fn test_tag_pages() {
+        let config = config();
+        let data = VecDeque::from([
+            data("a", "2024-01-01", &["Rust", "入門"]),
+            data("b", "2024-02-01", &["Rust"]),
+            data("c", "2024-03-01", &[]),
+        ]);
+        let template = Template::parse(
+            "{{ $tag.name }}({{ $count }}):\
+             {% for post in $posts %} {{ $post.title }}\
+             {% for tag in $post.tags %}[{{ $tag.url }}]{% endfor %}{% endfor %}",
+        )
+        .unwrap();
+
+        let pages = tag_pages(&config, &data)
+            .unwrap()
+            .into_iter()
+            .map(|(path, context)| (path, template.render(&context)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pages,
+            [
+                (
+                    PathBuf::from("tags/Rust/index.html"),
+                    "Rust(2): B[/tags/Rust/] A[/tags/Rust/][/tags/nyumon/]".to_string()
+                ),
+                (
+                    PathBuf::from("tags/nyumon/index.html"),
+                    "入門(1): A[/tags/Rust/][/tags/nyumon/]".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tag_pages_invalid_slug() {
+        let invalid = |config: &Config, tag: &str| {
+            let data = VecDeque::from([data("a", "2024-01-01", &["Rust", tag])]);
+            match tag_pages(config, &data) {
+                Err(Error::InvalidTagSlug { tag, slug }) => Some((tag, slug)),
+                _ => None,
+            }
+        };
+        let config = config();
+        assert_eq!(
+            invalid(&config, ".."),
+            Some(("..".to_string(), "..".to_string()))
+        );
+        assert_eq!(
+            invalid(&config, "."),
+            Some((".".to_string(), ".".to_string()))
+        );
+        assert_eq!(invalid(&config, "日記"), None);
+
+        // `tag_slugs`の値も確かめる
+        let config = Config {
+            tag_slugs: BTreeMap::from([
+                ("入門".to_string(), String::new()),
+                ("日記".to_string(), "../index".to_string()),
+                ("雑記".to_string(), "..".to_string()),
+            ]),
+            ..Config::default()
+        };
+        assert_eq!(
+            invalid(&config, "入門"),
+            Some(("入門".to_string(), String::new()))
+        );
+        assert_eq!(
+            invalid(&config, "日記"),
+            Some(("日記".to_string(), "../index".to_string()))
+        );
+        assert_eq!(
+            invalid(&config, "雑記"),
+            Some(("雑記".to_string(), "..".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_tag_pages_duplicate_slug() {
+        let duplicate = |config: &Config, tags: &[&str]| {
+            let data = VecDeque::from([data("a", "2024-01-01", tags)]);
+            match tag_pages(config, &data) {
+                Err(Error::DuplicateTagSlug { tags, slug }) => Some((tags, slug)),
+                _ => None,
+            }
+        };
+        let config = config();
+        assert_eq!(
+            duplicate(&config, &["CI/CD", "CI-CD"]),
+            Some((
+                ("CI-CD".to_string(), "CI/CD".to_string()),
+                "CI-CD".to_string()
+            ))
+        );
+        assert_eq!(
+            duplicate(&config, &["入門", "nyumon"]),
+            Some((
+                ("nyumon".to_string(), "入門".to_string()),
+                "nyumon".to_string()
+            ))
+        );
+        assert_eq!(
+            duplicate(&config, &["Rust", "rust"]),
+            Some((("Rust".to_string(), "rust".to_string()), "rust".to_string()))
+        );
+        assert_eq!(duplicate(&config, &["Rust", "入門"]), None);
+    }
+
+    #[test]
+    fn test_tag_cloud() {
+        let config = config();
+        let template = Template::parse(
+            "{% for tag in $tags %}<a href=\"{{ $tag.url }}\">{{ $tag.name }}</a>{{ $tag.count }}{% endfor %}",
+        )
+        .unwrap();
+        let data = VecDeque::from([
+            data("a", "2024-01-01", &["日記", "入門"]),
+            data("b", "2024-02-01", &["入門"]),
+        ]);
+        assert_eq!(
+            template.render(&tag_cloud(&config, &data)),
+            "<a href=\"/tags/nyumon/\">入門</a>2<a href=\"/tags/%E6%97%A5%E8%A8%98/\">日記</a>1"
+        );
+    }
+
+    #[test]
+    fn test_write_tags() {
+        let dir = std::env::temp_dir().join(format!("blog-parser-tags-{}", std::process::id()));
+        let config = Config {
+            output_dir: dir.join("public"),
+            tag_template: dir.join("tag.html"),
+            tag_cloud_template: dir.join("tags.html"),
+            ..config()
+        };
+        fs::create_dir_all(config.output_dir.join("tags/howto")).unwrap();
+        fs::write(&config.tag_template, "{{ $tag.name }}").unwrap();
+        fs::write(&config.tag_cloud_template, "{{ $tags }}").unwrap();
+        // `content/tags/howto.md`の記事
+        fs::write(config.output_dir.join("tags/howto/index.html"), "howto").unwrap();
+        let read = |path: &str| fs::read_to_string(config.output_dir.join(path)).ok();
+        let mut cache = TemplateCache::new();
+        let mut manifest = Manifest::default();
+
+        let posts = VecDeque::from([data("a", "2024-01-01", &["Rust", "入門"])]);
+        assert_eq!(
+            write_tags(&config, &mut cache, &posts, &mut manifest).unwrap(),
+            2
+        );
+        assert_eq!(read("tags/Rust/index.html").as_deref(), Some("Rust"));
+        assert_eq!(read("tags/nyumon/index.html").as_deref(), Some("入門"));
+        assert_eq!(manifest.tags.len(), 3);
+
+        // なくなったタグのページだけを消す
+        let posts = VecDeque::from([data("a", "2024-01-01", &["入門"])]);
+        assert_eq!(
+            write_tags(&config, &mut cache, &posts, &mut manifest).unwrap(),
+            1
+        );
+        assert!(!config.output_dir.join("tags/Rust").exists());
+        assert_eq!(read("tags/nyumon/index.html").as_deref(), Some("入門"));
+        assert!(read("tags/index.html").is_some());
+        assert_eq!(read("tags/howto/index.html").as_deref(), Some("howto"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
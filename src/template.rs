@@ -0,0 +1,714 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+
+use self::{
+    filter::{escape_html, Filter},
+    syntax::{Loader, Source},
+};
+
+pub mod cache;
+pub mod filter;
+mod syntax;
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    String(String),
+    List(Vec<Value>),
+    /// `{{ $post.title }}`のように`.`で値を取り出す
+    Map(BTreeMap<String, Value>),
+}
+
+/// 変数名と値。front matterのキーのように実行時に決まる変数も入れられる
+pub type Context = HashMap<String, Value>;
+
+impl Value {
+    /// `{% if %}`の判定。空文字列と空のリスト・マップは偽
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::String(string) => !string.is_empty(),
+            Self::List(list) => !list.is_empty(),
+            Self::Map(map) => !map.is_empty(),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Self::String(string) => string.clone(),
+            Self::List(list) => list
+                .iter()
+                .map(Value::to_text)
+                .collect::<Vec<_>>()
+                .join(", "),
+            Self::Map(_) => serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+}
+
+pub trait ToValue {
+    fn to_value(&self) -> Value;
+}
+
+impl ToValue for str {
+    fn to_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+}
+
+impl<T: ToValue> ToValue for [T] {
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(ToValue::to_value).collect())
+    }
+}
+
+impl<T: ToValue> ToValue for Vec<T> {
+    fn to_value(&self) -> Value {
+        self.as_slice().to_value()
+    }
+}
+
+impl<T: ToValue> ToValue for BTreeMap<String, T> {
+    fn to_value(&self) -> Value {
+        Value::Map(
+            self.iter()
+                .map(|(key, value)| (key.clone(), value.to_value()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: ToValue> ToValue for HashMap<String, T> {
+    fn to_value(&self) -> Value {
+        Value::Map(
+            self.iter()
+                .map(|(key, value)| (key.clone(), value.to_value()))
+                .collect(),
+        )
+    }
+}
+
+impl ToValue for Value {
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl<T: ToValue + ?Sized> ToValue for &T {
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Variable {
+        name: String,
+        filters: Vec<Filter>,
+        /// 値がない場合はこの元のテキストをそのまま出力する
+        source: String,
+        location: Location,
+    },
+    If {
+        name: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    For {
+        item: String,
+        name: String,
+        body: Vec<Node>,
+        location: Location,
+    },
+    /// `{% extends %}`した子テンプレートで中身を置き換えられる部分
+    Block {
+        name: String,
+        body: Vec<Node>,
+    },
+}
+
+/// 厳密モードのエラー表示用の、テンプレート中の位置
+#[derive(Debug, PartialEq)]
+struct Location {
+    template: Option<String>,
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    fn error(&self, message: String) -> Error {
+        Error::Template {
+            template: self.template.clone(),
+            message,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+/// `{{ $name | filter }}`の置換と`{% if $name %}`・`{% for item in $name %}`のブロックを持つテンプレート。
+/// `Template::load`で読み込んだ場合は`{% include %}`と`{% extends %}`も使える
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+    /// `{% include %}`などで読み込んだファイルも含めた、テンプレートのファイル
+    files: Vec<PathBuf>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template> {
+        let nodes = Loader::new(None).parse(&Source {
+            name: None,
+            text: source,
+        })?;
+        Ok(Template {
+            nodes,
+            files: Vec::new(),
+        })
+    }
+
+    /// `dir`にある`name`のテンプレートを読み込む。
+    /// `{% include %}`と`{% extends %}`のファイル名も`dir`からの相対パスで解決する
+    pub fn load(dir: impl AsRef<Path>, name: &str) -> Result<Template> {
+        let mut loader = Loader::new(Some(dir.as_ref()));
+        let nodes = loader.load(name, None)?;
+        Ok(Template {
+            nodes,
+            files: loader.files,
+        })
+    }
+
+    /// `path`のテンプレートを読み込む。
+    /// `{% include %}`などのファイル名は`path`のディレクトリからの相対パス
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Template> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let name = path
+            .file_name()
+            .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+        Template::load(dir, &name)
+    }
+
+    /// 読み込んだファイルのパス。`Template::parse`の場合は空
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// 値のない`{{ $name }}`は元のテキストのまま出力する
+    pub fn render(&self, variables: &Context) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, variables, &mut Vec::new(), false, &mut output)
+            .expect("rendering without strict mode never fails");
+        output
+    }
+
+    /// 値のない`{{ $name }}`と`{% for item in $name %}`をその位置のエラーにする。
+    /// `{% if $name %}`はfront matterのように記事によってない値を確かめるためのものなので、値がなければ偽にする。
+    /// テンプレートで使われない変数の名前(昇順)も返す
+    pub fn render_strict(&self, variables: &Context) -> Result<(String, Vec<String>)> {
+        let mut output = String::new();
+        render_nodes(&self.nodes, variables, &mut Vec::new(), true, &mut output)?;
+
+        let mut used = BTreeSet::new();
+        collect_names(&self.nodes, &mut used);
+        let mut unused = variables
+            .keys()
+            .filter(|name| !used.contains(name.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        unused.sort();
+        Ok((output, unused))
+    }
+}
+
+/// 分岐やループの中も含めて、テンプレートが参照する変数の名前を集める
+fn collect_names<'a>(nodes: &'a [Node], names: &mut BTreeSet<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Variable { name, .. } => {
+                names.insert(root_name(name));
+            }
+            Node::If {
+                name,
+                then,
+                otherwise,
+            } => {
+                names.insert(root_name(name));
+                collect_names(then, names);
+                collect_names(otherwise, names);
+            }
+            Node::For { name, body, .. } => {
+                names.insert(root_name(name));
+                collect_names(body, names);
+            }
+            Node::Block { body, .. } => collect_names(body, names),
+        }
+    }
+}
+
+/// `locals`は`{% for %}`で束縛された変数(内側のものほど後ろ)。
+/// `strict`の場合は値のない`{{ $name }}`と`{% for %}`でエラーにする
+fn render_nodes(
+    nodes: &[Node],
+    variables: &Context,
+    locals: &mut Vec<(String, Value)>,
+    strict: bool,
+    output: &mut String,
+) -> Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable {
+                name,
+                filters,
+                source,
+                location,
+            } => match lookup(name, variables, locals) {
+                Some(value) => {
+                    // `escape`の後ろに`upper`などが続いてもエスケープ済みのまま
+                    let (value, escaped) =
+                        filters
+                            .iter()
+                            .fold((value.clone(), false), |(value, escaped), filter| {
+                                (filter.apply(value), escaped || *filter == Filter::Escape)
+                            });
+                    let value = value.to_text();
+                    // `escape`済みか`safe`の場合以外はHTMLとしてエスケープする
+                    if escaped || filters.contains(&Filter::Safe) {
+                        output.push_str(&value)
+                    } else {
+                        output.push_str(&escape_html(&value))
+                    }
+                }
+                None if strict => {
+                    return Err(location.error(format!("undefined variable `{}`", name)))
+                }
+                None => output.push_str(source),
+            },
+            Node::If {
+                name,
+                then,
+                otherwise,
+            } => {
+                let truthy = lookup(name, variables, locals).is_some_and(Value::is_truthy);
+                let nodes = if truthy { then } else { otherwise };
+                render_nodes(nodes, variables, locals, strict, output)?;
+            }
+            Node::For {
+                item,
+                name,
+                body,
+                location,
+            } => {
+                let items = match lookup(name, variables, locals) {
+                    Some(Value::List(items)) => items.clone(),
+                    Some(value) => vec![value.clone()],
+                    None if strict => {
+                        return Err(location.error(format!("undefined variable `{}`", name)))
+                    }
+                    None => Vec::new(),
+                };
+                for value in items {
+                    locals.push((item.clone(), value));
+                    let result = render_nodes(body, variables, locals, strict, output);
+                    locals.pop();
+                    result?;
+                }
+            }
+            Node::Block { body, .. } => render_nodes(body, variables, locals, strict, output)?,
+        }
+    }
+    Ok(())
+}
+
+/// `post.title`の`post`
+fn root_name(name: &str) -> &str {
+    name.split('.').next().unwrap_or(name)
+}
+
+/// `post.title`のような`.`区切りの名前はマップの中を順にたどる
+fn lookup<'a>(
+    name: &str,
+    variables: &'a Context,
+    locals: &'a [(String, Value)],
+) -> Option<&'a Value> {
+    let mut keys = name.split('.');
+    let root = keys.next()?;
+    let value = locals
+        .iter()
+        .rev()
+        .find(|(local, _)| local == root)
+        .map(|(_, value)| value)
+        .or_else(|| variables.get(root))?;
+    keys.try_fold(value, |value, key| match value {
+        Value::Map(map) => map.get(key),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        fs,
+        path::PathBuf,
+    };
+
+    use crate::{
+        error::Error,
+        template::{Template, ToValue, Value},
+    };
+
+    fn template_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("blog-parser-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, source) in files {
+            fs::write(dir.join(file), source).unwrap();
+        }
+        dir
+    }
+
+    fn render(template: &str, variables: &[(&str, Value)]) -> String {
+        let variables = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect::<HashMap<_, _>>();
+        Template::parse(template).unwrap().render(&variables)
+    }
+
+    #[test]
+    fn test_render() {
+        let tags = vec!["Rust", "ブログ"].to_value();
+        let post = BTreeMap::from([
+            ("title".to_string(), "記事".to_value()),
+            (
+                "author".to_string(),
+                BTreeMap::from([("name".to_string(), "alice")]).to_value(),
+            ),
+        ])
+        .to_value();
+        let posts = vec![
+            HashMap::from([("title".to_string(), "一つ目")]),
+            HashMap::from([("title".to_string(), "二つ目")]),
+            HashMap::new(),
+        ]
+        .to_value();
+        let tests = [
+            (
+                "<title>{{ $title }}</title>{{$title}}",
+                "<title>タイトル</title>タイトル",
+            ),
+            ("{{ $undefined }} {{ title }} {{", "{{ $undefined }} {{ title }} {{"),
+            (
+                "{% if $description %}<meta content=\"{{ $description }}\">{% endif %}",
+                "<meta content=\"説明\">",
+            ),
+            ("{% if $empty %}あり{% else %}なし{% endif %}", "なし"),
+            ("{% if $undefined %}あり{% else %}なし{% endif %}", "なし"),
+            (
+                "<ul>{% for tag in $tags %}<li>{{ $tag }}</li>{% endfor %}</ul>",
+                "<ul><li>Rust</li><li>ブログ</li></ul>",
+            ),
+            (
+                "{% for tag in $tags %}{% if $tag %}{{ $title }}:{{ $tag }} {% endif %}{% endfor %}",
+                "タイトル:Rust タイトル:ブログ ",
+            ),
+            ("{% for tag in $empty_list %}{{ $tag }}{% endfor %}", ""),
+            ("{{ $tags }}", "Rust, ブログ"),
+            (
+                "<meta content=\"{{ $quoted }}\">{{ $html }}",
+                "<meta content=\"&quot;引用&quot; &amp; &lt;b&gt;\">&lt;p&gt;本文&lt;/p&gt;",
+            ),
+            ("{{ $html | safe }}", "<p>本文</p>"),
+            ("{{ $quoted | escape }}", "&quot;引用&quot; &amp; &lt;b&gt;"),
+            ("{{ $quoted | escape | upper }}", "&QUOT;引用&QUOT; &AMP; &LT;B&GT;"),
+            ("{{ $title | truncate(2) | safe }}", "タイ…"),
+            ("{{ $date | date(\"%Y年%m月%d日\") }}", "2024年04月01日"),
+            ("{{ $tags | json }}", "[&quot;Rust&quot;,&quot;ブログ&quot;]"),
+            ("{{ $tags | json | safe }}", "[\"Rust\",\"ブログ\"]"),
+            ("{{ $query | urlencode | upper }}", "RUST%20%E5%85%A5%E9%96%80"),
+            ("{{ $undefined | upper }}", "{{ $undefined | upper }}"),
+            (
+                "{{ $post.title }}/{{ $post.author.name | upper }}/{{ $post.missing }}/{{ $title.x }}",
+                "記事/ALICE/{{ $post.missing }}/{{ $title.x }}",
+            ),
+            (
+                "{% for post in $posts %}{% if $post.title %}{{ $post.title }},{% endif %}{% endfor %}",
+                "一つ目,二つ目,",
+            ),
+            ("{{ $post.author | json | safe }}", "{\"name\":\"alice\"}"),
+        ];
+        for (template, html) in tests {
+            assert_eq!(
+                render(
+                    template,
+                    &[
+                        ("title", "タイトル".to_value()),
+                        ("description", "説明".to_value()),
+                        ("empty", "".to_value()),
+                        ("tags", tags.clone()),
+                        ("empty_list", Vec::<String>::new().to_value()),
+                        ("quoted", "\"引用\" & <b>".to_value()),
+                        ("html", "<p>本文</p>".to_value()),
+                        ("date", "2024-04-01".to_value()),
+                        ("query", "rust 入門".to_value()),
+                        ("post", post.clone()),
+                        ("posts", posts.clone()),
+                    ]
+                ),
+                html
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_strict() {
+        let variables = [
+            ("title".to_string(), "タイトル".to_value()),
+            ("tags".to_string(), vec!["Rust"].to_value()),
+            ("description".to_string(), "".to_value()),
+            ("date".to_string(), "2024-04-01".to_value()),
+            ("author".to_string(), "著者".to_value()),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        let template = Template::parse(
+            "{{ $title }}{% for tag in $tags %}#{{ $tag }}{% endfor %}\
+             {% if $description %}{{ $description }}{% endif %}",
+        )
+        .unwrap();
+        assert_eq!(
+            template.render_strict(&variables).unwrap(),
+            (
+                "タイトル#Rust".to_string(),
+                vec!["author".to_string(), "date".to_string()]
+            )
+        );
+
+        let template = Template::parse("{{ $title }}\n<p>{{ $autor | upper }}</p>").unwrap();
+        assert_eq!(
+            template.render(&variables),
+            "タイトル\n<p>{{ $autor | upper }}</p>"
+        );
+        match template.render_strict(&variables) {
+            Err(Error::Template {
+                template: None,
+                message,
+                line,
+                column,
+            }) => assert_eq!(
+                (message.as_str(), line, column),
+                ("undefined variable `autor`", 2, 4)
+            ),
+            result => panic!("expected template error: {:?}", result),
+        }
+
+        // `for`の値がなければエラーにするが、`if`は偽として扱う
+        let template = Template::parse(
+            "{% if $draft %}下書き{% endif %}\n  {% for tag in $tgas %}{% endfor %}",
+        )
+        .unwrap();
+        assert_eq!(template.render(&variables), "\n  ");
+        assert_eq!(
+            template.render_strict(&variables).unwrap_err().to_string(),
+            "template error at 2:3: undefined variable `tgas`"
+        );
+        let template = Template::parse(
+            "{% if $draft %}下書き{% else %}公開{% endif %}{% for tag in $tags %}{% for c in $tag.chars %}{% endfor %}{% endfor %}",
+        )
+        .unwrap();
+        assert_eq!(
+            template.render_strict(&variables).unwrap_err().to_string(),
+            "template error at 1:64: undefined variable `tag.chars`"
+        );
+        let template = Template::parse("{% if $draft %}下書き{% else %}公開{% endif %}").unwrap();
+        assert_eq!(template.render_strict(&variables).unwrap().0, "公開");
+
+        let dir = template_dir(
+            "render-strict",
+            &[
+                ("footer.html", "\n<footer>{{ $autor }}</footer>"),
+                ("page.html", "{{ $title }}{% include \"footer.html\" %}"),
+            ],
+        );
+        let error = Template::load(&dir, "page.html")
+            .unwrap()
+            .render_strict(&variables)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "template error at footer.html:2:9: undefined variable `autor`"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let tests = [
+            ("{% if $title %}", "missing `{% endif %}`", 1, 1),
+            ("\n  {% endif %}", "unexpected `{% endif %}`", 2, 3),
+            (
+                "{% for tag in $tags %}\n{% endif %}",
+                "expected `{% endfor %}` but found `{% endif %}`",
+                2,
+                1,
+            ),
+            ("{% unknown %}", "unknown tag `{% unknown %}`", 1, 1),
+            (
+                "{% if title %}{% endif %}",
+                "expected a variable like `$name` but found `title`",
+                1,
+                1,
+            ),
+            ("テンプレート{% if", "unclosed `{%`", 1, 7),
+            ("\n {{ $title | lower }}", "unknown filter `lower`", 2, 2),
+            (
+                "{{ $title | truncate }}",
+                "`truncate` filter needs an argument",
+                1,
+                1,
+            ),
+            (
+                "{% include \"header.html\" %}",
+                "`{% include %}` needs a template directory; use `Template::load`",
+                1,
+                1,
+            ),
+            (
+                "{% if $title %}{% extends \"base.html\" %}{% endif %}",
+                "`{% extends %}` must be at the top level of the template",
+                1,
+                16,
+            ),
+        ];
+        for (template, expected, expected_line, expected_column) in tests {
+            match Template::parse(template) {
+                Err(Error::Template {
+                    template: None,
+                    message,
+                    line,
+                    column,
+                }) => {
+                    assert_eq!(
+                        (message.as_str(), line, column),
+                        (expected, expected_line, expected_column)
+                    )
+                }
+                result => panic!("expected template error: {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = template_dir(
+            "load",
+            &[
+                ("header.html", "<header>{{ $title }}</header>"),
+                (
+                    "base.html",
+                    "{% include \"header.html\" %}\
+                     <main>{% block main %}本文なし{% endblock %}</main>\
+                     <footer>{% block footer %}フッター{% endblock %}</footer>",
+                ),
+                (
+                    "article.html",
+                    "{% extends \"base.html\" %}\
+                     無視される\
+                     {% block main %}{% block body %}{{ $content }}{% endblock %}{% endblock %}",
+                ),
+                (
+                    "page.html",
+                    "{% extends 'article.html' %}{% block body %}固定ページ{% endblock %}",
+                ),
+            ],
+        );
+        let variables = [
+            ("title".to_string(), "タイトル".to_value()),
+            ("content".to_string(), "本文".to_value()),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        assert_eq!(
+            Template::load(&dir, "article.html")
+                .unwrap()
+                .render(&variables),
+            "<header>タイトル</header><main>本文</main><footer>フッター</footer>"
+        );
+        assert_eq!(
+            Template::load(&dir, "page.html")
+                .unwrap()
+                .render(&variables),
+            "<header>タイトル</header><main>固定ページ</main><footer>フッター</footer>"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_error() {
+        let dir = template_dir(
+            "load-error",
+            &[
+                ("a.html", "{% include \"b.html\" %}"),
+                ("b.html", "\n{% extends \"a.html\" %}"),
+                ("broken.html", "{% if $title %}"),
+                ("includes-broken.html", "{% include \"broken.html\" %}"),
+                ("missing.html", "{% include \"nothing.html\" %}"),
+            ],
+        );
+        let tests = [
+            (
+                "a.html",
+                "b.html",
+                "template cycle: a.html -> b.html -> a.html",
+                2,
+                1,
+            ),
+            (
+                "includes-broken.html",
+                "broken.html",
+                "missing `{% endif %}`",
+                1,
+                1,
+            ),
+        ];
+        for (name, expected_template, expected, expected_line, expected_column) in tests {
+            match Template::load(&dir, name) {
+                Err(Error::Template {
+                    template: Some(template),
+                    message,
+                    line,
+                    column,
+                }) => {
+                    assert_eq!(
+                        (template.as_str(), message.as_str(), line, column),
+                        (expected_template, expected, expected_line, expected_column)
+                    )
+                }
+                result => panic!("expected template error: {:?}", result),
+            }
+        }
+        match Template::load(&dir, "missing.html") {
+            Err(Error::Template { message, .. }) => {
+                assert!(message.starts_with("failed to read `nothing.html`: "))
+            }
+            result => panic!("expected template error: {:?}", result),
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
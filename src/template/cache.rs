@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use super::Template;
+use crate::error::Result;
+
+struct Cached {
+    template: Arc<Template>,
+    /// `template.files()`それぞれの読み込んだ時点の更新日時
+    modified: Vec<Option<SystemTime>>,
+}
+
+/// 読み込んだテンプレートをパスごとに保持する。
+/// `{% include %}`などで読んだファイルも含めて更新日時が変わったら読み直す
+#[derive(Default)]
+pub struct TemplateCache {
+    templates: HashMap<PathBuf, Cached>,
+}
+
+impl TemplateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&mut self, path: impl AsRef<Path>) -> Result<Arc<Template>> {
+        let path = path.as_ref();
+        if let Some(cached) = self.templates.get(path) {
+            if modified(cached.template.files()) == cached.modified {
+                return Ok(Arc::clone(&cached.template));
+            }
+        }
+
+        let template = Arc::new(Template::load_file(path)?);
+        self.templates.insert(
+            path.to_path_buf(),
+            Cached {
+                modified: modified(template.files()),
+                template: Arc::clone(&template),
+            },
+        );
+        Ok(template)
+    }
+}
+
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use super::TemplateCache;
+    use crate::{context, template::Template};
+
+    #[test]
+    fn test_get() {
+        let dir = std::env::temp_dir().join(format!("blog-parser-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let header = dir.join("header.html");
+        let page = dir.join("page.html");
+        fs::write(&header, "<h1>{{ $title }}</h1>").unwrap();
+        fs::write(&page, "{% include \"header.html\" %}本文").unwrap();
+
+        let mut cache = TemplateCache::new();
+        let template = cache.get(&page).unwrap();
+        assert_eq!(template.files(), [page.clone(), header.clone()]);
+        assert!(Arc::ptr_eq(&template, &cache.get(&page).unwrap()));
+
+        // 更新日時の分解能によらず変更を検出できるように、更新日時を明示的にずらす
+        fs::write(&header, "<h2>{{ $title }}</h2>").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&header)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        let reloaded = cache.get(&page).unwrap();
+        assert!(!Arc::ptr_eq(&template, &reloaded));
+        let title = "タイトル";
+        assert_eq!(reloaded.render(&context!(title)), "<h2>タイトル</h2>本文");
+        assert_eq!(*reloaded, Template::load(&dir, "page.html").unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+use std::fmt::{Display, Write};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, NaiveDate,
+};
+
+use super::Value;
+use crate::plain_text::truncate;
+
+/// `{{ $name | filter }}`のフィルター
+#[derive(Debug, PartialEq, Clone)]
+pub enum Filter {
+    Escape,
+    /// 自動のエスケープをしない
+    Safe,
+    Upper,
+    /// 書記素単位で指定の文字数に切り詰める
+    Truncate(usize),
+    /// `2024-01-01`やRFC 3339の日時を`chrono`の書式で表示する
+    Date(String),
+    Json,
+    UrlEncode,
+}
+
+impl Filter {
+    /// `|`で区切られたフィルターを読む。エラーの場合はメッセージを返す
+    pub fn parse_all(filters: &str) -> Result<Vec<Filter>, String> {
+        let mut parsed = Vec::new();
+        let mut rest = filters.trim();
+        while !rest.is_empty() {
+            let Some(filter) = rest.strip_prefix('|') else {
+                return Err(format!("expected `|` but found `{}`", rest));
+            };
+            let filter = filter.trim_start();
+            let name_len = filter
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(filter.len());
+            let (name, after) = filter.split_at(name_len);
+            let (argument, after) = match after.trim_start().strip_prefix('(') {
+                Some(argument) => {
+                    let (argument, after) = split_argument(argument)?;
+                    (Some(argument), after)
+                }
+                None => (None, after),
+            };
+            parsed.push(match (name, argument) {
+                ("escape", None) => Filter::Escape,
+                ("safe", None) => Filter::Safe,
+                ("upper", None) => Filter::Upper,
+                ("json", None) => Filter::Json,
+                ("urlencode", None) => Filter::UrlEncode,
+                ("truncate", Some(length)) => {
+                    Filter::Truncate(length.parse().map_err(|_| {
+                        format!("`truncate` expects a number but found `{}`", length)
+                    })?)
+                }
+                ("date", Some(format)) => {
+                    let format = unquote(format).ok_or_else(|| {
+                        format!("`date` expects a quoted format but found `{}`", format)
+                    })?;
+                    if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+                        return Err(format!("invalid date format `{}`", format));
+                    }
+                    Filter::Date(format)
+                }
+                ("truncate" | "date", None) => {
+                    return Err(format!("`{}` filter needs an argument", name))
+                }
+                ("escape" | "safe" | "upper" | "json" | "urlencode", Some(argument)) => {
+                    return Err(format!(
+                        "`{}` filter takes no argument but found `{}`",
+                        name, argument
+                    ))
+                }
+                _ => return Err(format!("unknown filter `{}`", name)),
+            });
+            rest = after.trim_start();
+        }
+        Ok(parsed)
+    }
+
+    pub fn apply(&self, value: Value) -> Value {
+        match self {
+            Self::Escape => Value::String(escape_html(&value.to_text())),
+            Self::Safe => value,
+            Self::Upper => Value::String(value.to_text().to_uppercase()),
+            Self::Truncate(length) => Value::String(truncate(&value.to_text(), *length)),
+            Self::Date(format) => {
+                let text = value.to_text();
+                // `2024-04-01`は0時として時刻も書けるようにする
+                let formatted = match DateTime::parse_from_rfc3339(&text) {
+                    Ok(date) => write_date(date.format(format)),
+                    Err(_) => NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                        .and_then(|date| write_date(date.format(format))),
+                };
+                // 日付として読めない値や、`%z`のように書けない書式はそのまま出す
+                Value::String(formatted.unwrap_or(text))
+            }
+            Self::Json => Value::String(serde_json::to_string(&value).unwrap_or_default()),
+            Self::UrlEncode => Value::String(url_encode(&value.to_text())),
+        }
+    }
+}
+
+/// `chrono`が書けない書式(`2024-04-01`の`%z`など)なら`None`
+fn write_date(date: impl Display) -> Option<String> {
+    let mut text = String::new();
+    write!(text, "{}", date).ok()?;
+    Some(text)
+}
+
+/// `(`の後ろから`)`までの引数と、`)`より後ろを分ける
+fn split_argument(argument: &str) -> Result<(&str, &str), String> {
+    let mut quote = None;
+    for (i, c) in argument.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, ')') => return Ok((argument[..i].trim(), &argument[i + 1..])),
+            _ => {}
+        }
+    }
+    Err("missing `)`".to_string())
+}
+
+fn unquote(quoted: &str) -> Option<String> {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|q| quoted.strip_prefix(q)?.strip_suffix(q))
+        .map(str::to_string)
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 英数字と`-_.~`以外をUTF-8のバイトごとにパーセントエンコードする
+pub fn url_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_html, url_encode, Filter};
+    use crate::template::{ToValue, Value};
+
+    #[test]
+    fn test_parse_all() {
+        assert_eq!(
+            Filter::parse_all("| escape|upper | truncate( 80 ) | date(\"%Y年|%m月\") | json"),
+            Ok(vec![
+                Filter::Escape,
+                Filter::Upper,
+                Filter::Truncate(80),
+                Filter::Date("%Y年|%m月".to_string()),
+                Filter::Json,
+            ])
+        );
+        assert_eq!(Filter::parse_all(""), Ok(Vec::new()));
+        assert_eq!(
+            Filter::parse_all("| unknown"),
+            Err("unknown filter `unknown`".to_string())
+        );
+        assert_eq!(
+            Filter::parse_all("| truncate(abc)"),
+            Err("`truncate` expects a number but found `abc`".to_string())
+        );
+        assert_eq!(
+            Filter::parse_all("| date(%Y)"),
+            Err("`date` expects a quoted format but found `%Y`".to_string())
+        );
+        assert_eq!(
+            Filter::parse_all("| date(\"%Y/%Q\")"),
+            Err("invalid date format `%Y/%Q`".to_string())
+        );
+        assert_eq!(
+            Filter::parse_all("| truncate(80"),
+            Err("missing `)`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let apply = |filter: Filter, value: &str| filter.apply(value.to_value()).to_text();
+        assert_eq!(apply(Filter::Upper, "rust"), "RUST");
+        assert_eq!(apply(Filter::Truncate(3), "日本語の記事"), "日本語…");
+        assert_eq!(
+            apply(Filter::Date("%Y年%m月%d日".to_string()), "2024-04-01"),
+            "2024年04月01日"
+        );
+        assert_eq!(
+            apply(
+                Filter::Date("%Y/%m/%d %H:%M".to_string()),
+                "2024-04-01T09:30:00+09:00"
+            ),
+            "2024/04/01 09:30"
+        );
+        assert_eq!(apply(Filter::Date("%Y".to_string()), "不明"), "不明");
+        // 日付だけの値は0時として時刻を書き、タイムゾーンのように書けない書式は元の値のまま
+        assert_eq!(
+            apply(Filter::Date("%Y/%m/%d %H:%M".to_string()), "2024-04-01"),
+            "2024/04/01 00:00"
+        );
+        assert_eq!(
+            apply(Filter::Date("%Y-%m-%d %z".to_string()), "2024-04-01"),
+            "2024-04-01"
+        );
+        assert_eq!(apply(Filter::Json, "\"引用\""), "\"\\\"引用\\\"\"");
+        assert_eq!(
+            Filter::Json.apply(vec!["a", "b"].to_value()),
+            Value::String("[\"a\",\"b\"]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">'&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_url_encode() {
+        assert_eq!(
+            url_encode("Rust 入門-1.0_~"),
+            "Rust%20%E5%85%A5%E9%96%80-1.0_~"
+        );
+    }
+}
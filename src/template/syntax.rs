@@ -0,0 +1,422 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{filter::Filter, Location, Node};
+use crate::error::{Error, Result};
+
+/// テンプレートのテキストと、エラー表示用のファイル名
+pub struct Source<'a> {
+    pub name: Option<&'a str>,
+    pub text: &'a str,
+}
+
+impl Source<'_> {
+    /// `offset`バイト目の行・列
+    fn location(&self, offset: usize) -> Location {
+        let before = &self.text[..offset];
+        Location {
+            template: self.name.map(str::to_string),
+            line: before.matches('\n').count() + 1,
+            column: before.chars().rev().take_while(|&c| c != '\n').count() + 1,
+        }
+    }
+
+    /// `offset`バイト目の行・列を付けたエラー
+    fn error(&self, offset: usize, message: String) -> Error {
+        self.location(offset).error(message)
+    }
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    /// `{{ }}`の変数と、`{{`の位置(バイト)
+    Variable {
+        name: &'a str,
+        filters: Vec<Filter>,
+        source: &'a str,
+        offset: usize,
+    },
+    /// `{% %}`の中身と、`{%`の位置(バイト)
+    Tag(&'a str, usize),
+}
+
+/// `{% include %}`と`{% extends %}`のファイルを`dir`から読み込む
+pub struct Loader<'a> {
+    dir: Option<&'a Path>,
+    /// 読み込み中のテンプレート名(循環の検出用)
+    stack: Vec<String>,
+    /// これまでに読み込んだファイル(キャッシュの更新の確認用)
+    pub files: Vec<PathBuf>,
+}
+
+impl<'a> Loader<'a> {
+    pub fn new(dir: Option<&'a Path>) -> Self {
+        Loader {
+            dir,
+            stack: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// `from`は`{% include %}`などでの読み込み元とその位置
+    pub fn load(&mut self, name: &str, from: Option<(&Source, usize)>) -> Result<Vec<Node>> {
+        let Some(dir) = self.dir else {
+            let message = "templates parsed from a string cannot load other templates".to_string();
+            return Err(match from {
+                Some((source, offset)) => source.error(offset, message),
+                None => Error::Template {
+                    template: None,
+                    message,
+                    line: 1,
+                    column: 1,
+                },
+            });
+        };
+
+        if self.stack.iter().any(|loading| loading == name) {
+            let cycle = self
+                .stack
+                .iter()
+                .skip_while(|loading| *loading != name)
+                .chain([&name.to_string()])
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let message = format!("template cycle: {}", cycle);
+            return Err(match from {
+                Some((source, offset)) => source.error(offset, message),
+                None => Error::Template {
+                    template: Some(name.to_string()),
+                    message,
+                    line: 1,
+                    column: 1,
+                },
+            });
+        }
+
+        let path = dir.join(name);
+        let text = match (fs::read_to_string(&path), from) {
+            (Ok(text), _) => text,
+            (Err(err), Some((source, offset))) => {
+                return Err(source.error(offset, format!("failed to read `{}`: {}", name, err)))
+            }
+            (Err(err), None) => return Err(err.into()),
+        };
+
+        if !self.files.contains(&path) {
+            self.files.push(path);
+        }
+        self.stack.push(name.to_string());
+        let nodes = self.parse(&Source {
+            name: Some(name),
+            text: &text,
+        });
+        self.stack.pop();
+        nodes
+    }
+
+    pub fn parse(&mut self, source: &Source) -> Result<Vec<Node>> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            position: 0,
+            loader: self,
+            extends: None,
+        };
+        let (mut nodes, end) = parser.parse_nodes(0)?;
+        if let Some((tag, offset)) = end {
+            return Err(source.error(offset, format!("unexpected `{{% {} %}}`", tag)));
+        }
+
+        let Some((parent, offset)) = parser.extends else {
+            return Ok(nodes);
+        };
+        // 子テンプレートはブロック以外を捨て、親テンプレートの同名のブロックを置き換える
+        let mut blocks = HashMap::new();
+        collect_blocks(&mut nodes, &mut blocks);
+        let mut parent_nodes = self.load(&parent, Some((source, offset)))?;
+        override_blocks(&mut parent_nodes, &mut blocks);
+        Ok(parent_nodes)
+    }
+}
+
+fn collect_blocks(nodes: &mut Vec<Node>, blocks: &mut HashMap<String, Vec<Node>>) {
+    for node in nodes.drain(..) {
+        match node {
+            Node::Block { name, body } => {
+                blocks.entry(name).or_insert(body);
+            }
+            Node::If {
+                mut then,
+                mut otherwise,
+                ..
+            } => {
+                collect_blocks(&mut then, blocks);
+                collect_blocks(&mut otherwise, blocks);
+            }
+            Node::For { mut body, .. } => collect_blocks(&mut body, blocks),
+            Node::Text(_) | Node::Variable { .. } => {}
+        }
+    }
+}
+
+fn override_blocks(nodes: &mut [Node], blocks: &mut HashMap<String, Vec<Node>>) {
+    for node in nodes {
+        match node {
+            Node::Block { name, body } => match blocks.remove(name) {
+                Some(replaced) => *body = replaced,
+                None => override_blocks(body, blocks),
+            },
+            Node::If {
+                then, otherwise, ..
+            } => {
+                override_blocks(then, blocks);
+                override_blocks(otherwise, blocks);
+            }
+            Node::For { body, .. } => override_blocks(body, blocks),
+            Node::Text(_) | Node::Variable { .. } => {}
+        }
+    }
+}
+
+fn tokenize<'a>(source: &Source<'a>) -> Result<Vec<Token<'a>>> {
+    let text = source.text;
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let start = match (text[i..].find("{{"), text[i..].find("{%")) {
+            (Some(a), Some(b)) => i + a.min(b),
+            (Some(a), None) | (None, Some(a)) => i + a,
+            (None, None) => {
+                tokens.push(Token::Text(&text[i..]));
+                break;
+            }
+        };
+        if start > i {
+            tokens.push(Token::Text(&text[i..start]));
+        }
+
+        if text[start..].starts_with("{{") {
+            let Some(end) = text[start..].find("}}").map(|end| start + end + 2) else {
+                tokens.push(Token::Text(&text[start..]));
+                break;
+            };
+            let inner = text[start + 2..end - 2].trim();
+            let variable = inner.strip_prefix('$').map(|name| {
+                name.split_at(
+                    name.find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '.')
+                        .unwrap_or(name.len()),
+                )
+            });
+            match variable {
+                Some((name, filters)) if is_path(name) => tokens.push(Token::Variable {
+                    name,
+                    filters: Filter::parse_all(filters)
+                        .map_err(|message| source.error(start, message))?,
+                    source: &text[start..end],
+                    offset: start,
+                }),
+                // `$`で始まらないものはテンプレートの記法ではないのでそのまま残す
+                _ => tokens.push(Token::Text(&text[start..end])),
+            }
+            i = end;
+        } else {
+            let Some(end) = text[start..].find("%}").map(|end| start + end + 2) else {
+                return Err(source.error(start, "unclosed `{%`".to_string()));
+            };
+            tokens.push(Token::Tag(text[start + 2..end - 2].trim(), start));
+            i = end;
+        }
+    }
+    Ok(tokens)
+}
+
+/// ブロックを終わらせたタグ(`else`・`endif`・`endfor`・`endblock`)とその位置。
+/// 入力の終わりなら`None`
+type BlockEnd<'a> = Option<(&'a str, usize)>;
+
+struct Parser<'s, 'l, 'd> {
+    source: &'s Source<'s>,
+    tokens: Vec<Token<'s>>,
+    position: usize,
+    loader: &'l mut Loader<'d>,
+    /// `{% extends %}`の親テンプレート名とその位置
+    extends: Option<(String, usize)>,
+}
+
+impl<'s> Parser<'s, '_, '_> {
+    /// ブロックの終わりか入力の終わりまでを読む。`depth`はブロックの入れ子の深さ
+    fn parse_nodes(&mut self, depth: usize) -> Result<(Vec<Node>, BlockEnd<'s>)> {
+        let mut nodes = Vec::new();
+        while let Some(token) = self.tokens.get(self.position) {
+            self.position += 1;
+            match *token {
+                Token::Text(text) => nodes.push(Node::Text(text.to_string())),
+                Token::Variable {
+                    name,
+                    ref filters,
+                    source,
+                    offset,
+                } => nodes.push(Node::Variable {
+                    name: name.to_string(),
+                    filters: filters.clone(),
+                    source: source.to_string(),
+                    location: self.source.location(offset),
+                }),
+                Token::Tag(tag, offset) => {
+                    let words = tag.split_whitespace().collect::<Vec<_>>();
+                    match words.as_slice() {
+                        ["if", variable] => {
+                            let name = self.variable_name(variable, offset)?;
+                            let (then, end) = self.parse_nodes(depth + 1)?;
+                            let otherwise = match end {
+                                Some(("else", _)) => {
+                                    let (otherwise, end) = self.parse_nodes(depth + 1)?;
+                                    self.expect_end(end, "endif", offset)?;
+                                    otherwise
+                                }
+                                end => {
+                                    self.expect_end(end, "endif", offset)?;
+                                    Vec::new()
+                                }
+                            };
+                            nodes.push(Node::If {
+                                name,
+                                then,
+                                otherwise,
+                            });
+                        }
+                        ["for", item, "in", variable] if is_identifier(item) => {
+                            let name = self.variable_name(variable, offset)?;
+                            let (body, end) = self.parse_nodes(depth + 1)?;
+                            self.expect_end(end, "endfor", offset)?;
+                            nodes.push(Node::For {
+                                item: item.to_string(),
+                                name,
+                                body,
+                                location: self.source.location(offset),
+                            });
+                        }
+                        ["block", name] if is_identifier(name) => {
+                            let (body, end) = self.parse_nodes(depth + 1)?;
+                            self.expect_end(end, "endblock", offset)?;
+                            nodes.push(Node::Block {
+                                name: name.to_string(),
+                                body,
+                            });
+                        }
+                        ["include", name] => {
+                            self.expect_loader(offset)?;
+                            let name = self.file_name(name, offset)?;
+                            nodes.extend(self.loader.load(&name, Some((self.source, offset)))?);
+                        }
+                        ["extends", name] => {
+                            if depth > 0 {
+                                return Err(self.source.error(
+                                    offset,
+                                    "`{% extends %}` must be at the top level of the template"
+                                        .to_string(),
+                                ));
+                            }
+                            if self.extends.is_some() {
+                                return Err(self.source.error(
+                                    offset,
+                                    "`{% extends %}` can only be used once".to_string(),
+                                ));
+                            }
+                            self.expect_loader(offset)?;
+                            self.extends = Some((self.file_name(name, offset)?, offset));
+                        }
+                        ["else" | "endif" | "endfor" | "endblock"] => {
+                            return Ok((nodes, Some((tag, offset))))
+                        }
+                        _ => {
+                            return Err(self
+                                .source
+                                .error(offset, format!("unknown tag `{{% {} %}}`", tag)))
+                        }
+                    }
+                }
+            }
+        }
+        Ok((nodes, None))
+    }
+
+    fn expect_loader(&self, offset: usize) -> Result<()> {
+        if self.loader.dir.is_some() {
+            return Ok(());
+        }
+        let tag = self.source.text[offset + 2..]
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        Err(self.source.error(
+            offset,
+            format!(
+                "`{{% {} %}}` needs a template directory; use `Template::load`",
+                tag
+            ),
+        ))
+    }
+
+    fn expect_end(&self, end: BlockEnd<'_>, expected: &str, start: usize) -> Result<()> {
+        match end {
+            Some((tag, _)) if tag == expected => Ok(()),
+            Some((tag, offset)) => Err(self.source.error(
+                offset,
+                format!(
+                    "expected `{{% {} %}}` but found `{{% {} %}}`",
+                    expected, tag
+                ),
+            )),
+            None => Err(self
+                .source
+                .error(start, format!("missing `{{% {} %}}`", expected))),
+        }
+    }
+
+    fn variable_name(&self, variable: &str, offset: usize) -> Result<String> {
+        match variable.strip_prefix('$') {
+            Some(name) if is_path(name) => Ok(name.to_string()),
+            _ => Err(self.source.error(
+                offset,
+                format!("expected a variable like `$name` but found `{}`", variable),
+            )),
+        }
+    }
+
+    /// `"header.html"`か`'header.html'`の引用符を外す
+    fn file_name(&self, quoted: &str, offset: usize) -> Result<String> {
+        quoted
+            .strip_prefix('"')
+            .and_then(|name| name.strip_suffix('"'))
+            .or_else(|| {
+                quoted
+                    .strip_prefix('\'')
+                    .and_then(|name| name.strip_suffix('\''))
+            })
+            .map(str::to_string)
+            .ok_or_else(|| {
+                self.source.error(
+                    offset,
+                    format!("expected a quoted file name but found `{}`", quoted),
+                )
+            })
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `post.title`のように`.`で区切った識別子
+fn is_path(name: &str) -> bool {
+    name.split('.').all(is_identifier)
+}
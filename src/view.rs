@@ -1,26 +1,59 @@
-pub mod __export {
-    pub use ::regex::Regex;
+use crate::{
+    error::Result,
+    template::{Context, Template},
+};
+
+/// 変数名をキーにして値を集めた`Context`
+#[macro_export]
+macro_rules! context {
+($($x:ident),+ $(,)?) => {{
+    let mut variables = $crate::template::Context::new();
+    $(
+      variables.insert(
+          stringify!($x).to_string(),
+          $crate::template::ToValue::to_value(&$x),
+      );
+    )*
+    variables
+}};
 }
-use std::fs;
 
+/// `$content`をテンプレートとして、同名の変数の値で描画した結果に置き換える。
+/// テンプレートの構文エラーの場合は`Err`になる。
+/// `compact!(strict html, ...)`の場合は値のない変数もエラーにし、使われなかった変数の名前を返す
 #[macro_export]
 macro_rules! compact {
+(strict $content:ident, $($x:ident),+) => {
+    $crate::template::Template::parse(&$content)
+        .and_then(|template| template.render_strict(&$crate::context!($($x),+)))
+        .map(|(rendered, unused)| {
+            $content = rendered;
+            unused
+        })
+};
 ($content:ident, $($x:ident),+) => {
-    $(
-      let re_string = format!(r"\{{\{{\s*\${}\s*\}}\}}", stringify!($x));
-      let re = $crate::view::__export::Regex::new(&re_string).unwrap();
-      let escaped = $x.replace("$", "$$"); // 置換文字列の$は特殊文字
-      $content = re.replace_all(&$content, escaped).into_owned();
-    )*
+    $crate::template::Template::parse(&$content).map(|template| {
+        $content = template.render(&$crate::context!($($x),+));
+    })
 };
 }
 
-pub fn view(file_path: &str) -> String {
-    fs::read_to_string(file_path).expect("Something went wrong reading the file")
+/// `file_path`のテンプレートを読み込む。
+/// `{% include %}`などのファイル名は`file_path`のディレクトリからの相対パス
+pub fn view(file_path: &str) -> Result<Template> {
+    Template::load_file(file_path)
+}
+
+/// `file_path`のテンプレートを`context`で描画する
+pub fn render(file_path: &str, context: &Context) -> Result<String> {
+    Ok(view(file_path)?.render(context))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::render;
+    use crate::template::{Context, ToValue};
+
     #[test]
     fn test_compact() {
         let mut html =
@@ -30,26 +63,83 @@ mod tests {
         let date = "Date";
         let description = "Description";
 
-        compact!(html, title, content, date, description);
+        compact!(html, title, content, date, description).unwrap();
 
         assert_eq!(html, "Title - Content - Date - Description");
+
+        let mut html = String::from(
+            "<meta name=\"description\" content=\"{{ $description }}\">{{ $content | safe }}",
+        );
+        let content = "<p>本文</p>";
+        let description = "\"Rust\"入門";
+        compact!(html, content, description).unwrap();
+        assert_eq!(
+            html,
+            "<meta name=\"description\" content=\"&quot;Rust&quot;入門\"><p>本文</p>"
+        );
     }
 
     #[test]
     fn test_compact_escape() {
         let mut output = String::from("{{ $text }}");
         let text = "$";
-        compact!(output, text);
+        compact!(output, text).unwrap();
         assert_eq!(output, "$");
 
         let mut output = String::from("{{ $text }}");
         let text = "$sample";
-        compact!(output, text);
+        compact!(output, text).unwrap();
         assert_eq!(output, "$sample");
 
         let mut output = String::from("{{ $text }}");
         let text = "$$sample $$sample";
-        compact!(output, text);
+        compact!(output, text).unwrap();
         assert_eq!(output, "$$sample $$sample");
     }
+
+    #[test]
+    fn test_compact_blocks() {
+        let mut html = String::from(
+            "{% if $description %}{{ $description }}{% else %}なし{% endif %}\
+             {% for tag in $tags %}#{{ $tag }}{% endfor %}",
+        );
+        let description = "";
+        let tags = vec!["Rust".to_string(), "ブログ".to_string()];
+        compact!(html, description, tags).unwrap();
+        assert_eq!(html, "なし#Rust#ブログ");
+
+        let mut html = String::from("{% if $description %}");
+        assert!(compact!(html, description).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let dir = std::env::temp_dir().join(format!("blog-parser-view-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("post.html");
+        std::fs::write(&path, "{{ $title }} by {{ $author }}").unwrap();
+
+        let mut context = Context::new();
+        for (key, value) in [("title", "タイトル"), ("author", "著者")] {
+            context.insert(key.to_string(), value.to_value());
+        }
+        assert_eq!(
+            render(path.to_str().unwrap(), &context).unwrap(),
+            "タイトル by 著者"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_compact_strict() {
+        let mut html = String::from("{{ $title }}");
+        let title = "Title";
+        let date = "Date";
+        assert_eq!(compact!(strict html, title, date).unwrap(), vec!["date"]);
+        assert_eq!(html, "Title");
+
+        let mut html = String::from("{{ $title }} {{ $autor }}");
+        assert!(compact!(strict html, title).is_err());
+        assert_eq!(html, "{{ $title }} {{ $autor }}");
+    }
 }
@@ -0,0 +1,147 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+/// ディレクトリ以下のファイルの更新日時を定期的に見比べて、変更を検出する
+pub struct Watcher {
+    dirs: Vec<PathBuf>,
+    ignore: Vec<PathBuf>,
+    files: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+impl Watcher {
+    /// `ignore`のファイルとディレクトリ(ビルドが書き出すものなど)は`dirs`の中にあっても見ない
+    pub fn new(dirs: Vec<PathBuf>, ignore: Vec<PathBuf>) -> Watcher {
+        let ignore = ignore
+            .iter()
+            .map(|path| normalize(path))
+            .collect::<Vec<_>>();
+        let files = snapshot(&dirs, &ignore);
+        Watcher {
+            dirs,
+            ignore,
+            files,
+        }
+    }
+
+    /// 前回の呼び出しから追加・削除・更新されたファイル
+    pub fn changes(&mut self) -> Vec<PathBuf> {
+        let files = snapshot(&self.dirs, &self.ignore);
+        let mut changes = files
+            .iter()
+            .filter(|(path, modified)| self.files.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        changes.extend(
+            self.files
+                .keys()
+                .filter(|path| !files.contains_key(*path))
+                .cloned(),
+        );
+        changes.sort();
+        self.files = files;
+        changes
+    }
+}
+
+/// `./public`と`public`を同じパスとして比べるため、`.`を取り除く
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+fn snapshot(dirs: &[PathBuf], ignore: &[PathBuf]) -> BTreeMap<PathBuf, Option<SystemTime>> {
+    let mut files = BTreeMap::new();
+    for dir in dirs {
+        walk(dir, ignore, &mut files);
+    }
+    files
+}
+
+/// 読めないディレクトリは無視する(監視中に消されることもあるため)
+fn walk(dir: &Path, ignore: &[PathBuf], files: &mut BTreeMap<PathBuf, Option<SystemTime>>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if ignore.contains(&normalize(&path)) {
+            continue;
+        }
+        if path.is_dir() {
+            walk(&path, ignore, files);
+        } else {
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            files.insert(path, modified);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    use super::Watcher;
+
+    #[test]
+    fn test_changes() {
+        let dir = std::env::temp_dir().join(format!("blog-parser-watch-{}", std::process::id()));
+        fs::create_dir_all(dir.join("content")).unwrap();
+        fs::create_dir_all(dir.join("templates")).unwrap();
+        let post = dir.join("content/post.md");
+        let template = dir.join("templates/post.html");
+        fs::write(&post, "# 記事").unwrap();
+        fs::write(&template, "{{ $title }}").unwrap();
+
+        let mut watcher =
+            Watcher::new(vec![dir.join("content"), dir.join("templates")], Vec::new());
+        assert!(watcher.changes().is_empty());
+
+        // 更新日時の分解能によらず変更を検出できるように、更新日時を明示的にずらす
+        fs::File::options()
+            .write(true)
+            .open(&template)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        let added = dir.join("content/2024/new.md");
+        fs::create_dir_all(added.parent().unwrap()).unwrap();
+        fs::write(&added, "# 新しい記事").unwrap();
+        assert_eq!(watcher.changes(), [added.clone(), template]);
+        assert!(watcher.changes().is_empty());
+
+        fs::remove_file(&post).unwrap();
+        assert_eq!(watcher.changes(), [post]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_changes_ignore() {
+        let dir =
+            std::env::temp_dir().join(format!("blog-parser-watch-ignore-{}", std::process::id()));
+        fs::create_dir_all(dir.join("public")).unwrap();
+        fs::write(dir.join("post.html"), "{{ $title }}").unwrap();
+
+        let mut watcher = Watcher::new(
+            vec![dir.clone()],
+            vec![dir.join("./public"), dir.join("data.json")],
+        );
+        fs::write(dir.join("public/index.html"), "一覧").unwrap();
+        fs::write(dir.join("data.json"), "[]").unwrap();
+        assert!(watcher.changes().is_empty());
+
+        fs::write(dir.join("index.html"), "{{ $page }}").unwrap();
+        assert_eq!(watcher.changes(), [dir.join("index.html")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::PathBuf,
+    thread,
+};
+
+use blog_parser::serve::{Reloader, Server, RELOAD_PATH};
+
+/// 1回のリクエストを送り、ステータス行とボディを返す
+fn request(addr: SocketAddr, method: &str, path: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        method, path
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.lines().next().unwrap().to_string();
+    (status, body.to_string())
+}
+
+fn start(name: &str) -> (SocketAddr, Reloader, PathBuf) {
+    let root = std::env::temp_dir().join(format!("blog-parser-{}-{}", name, std::process::id()));
+    fs::create_dir_all(root.join("2024/rust")).unwrap();
+    fs::write(root.join("index.html"), "<body>トップ</body>").unwrap();
+    fs::write(root.join("2024/rust/index.html"), "<p>記事</p>").unwrap();
+    fs::write(root.join("style.css"), "body {}").unwrap();
+
+    let server = Server::bind("127.0.0.1:0", &root).unwrap();
+    let addr = server.local_addr().unwrap();
+    let reloader = server.reloader();
+    thread::spawn(move || server.run());
+    (addr, reloader, root)
+}
+
+#[test]
+fn test_serve_files() {
+    let (addr, _, root) = start("serve-files");
+
+    let (status, body) = request(addr, "GET", "/");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(body.starts_with("<body>トップ<script>"));
+    assert!(body.ends_with("</script>\n</body>"));
+
+    let (status, body) = request(addr, "GET", "/2024/rust/?from=top");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(body.starts_with("<p>記事</p><script>"));
+
+    let (status, body) = request(addr, "GET", "/style.css");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(body, "body {}");
+
+    let (status, body) = request(addr, "HEAD", "/style.css");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(body, "");
+
+    assert_eq!(
+        request(addr, "GET", "/nothing/").0,
+        "HTTP/1.1 404 Not Found"
+    );
+    assert_eq!(
+        request(addr, "GET", "/../secret").0,
+        "HTTP/1.1 400 Bad Request"
+    );
+    assert_eq!(
+        request(addr, "GET", "/%2e%2e/secret").0,
+        "HTTP/1.1 400 Bad Request"
+    );
+    assert_eq!(
+        request(addr, "POST", "/").0,
+        "HTTP/1.1 405 Method Not Allowed"
+    );
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_live_reload() {
+    let (addr, reloader, root) = start("live-reload");
+
+    assert_eq!(request(addr, "GET", RELOAD_PATH).1, "0");
+    assert!(request(addr, "GET", "/")
+        .1
+        .contains("const version = \"0\";"));
+
+    reloader.reload();
+    assert_eq!(request(addr, "GET", RELOAD_PATH).1, "1");
+    assert!(request(addr, "GET", "/")
+        .1
+        .contains("const version = \"1\";"));
+    fs::remove_dir_all(root).unwrap();
+}